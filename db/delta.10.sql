@@ -0,0 +1,10 @@
+CREATE TABLE api_tokens (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username TEXT NOT NULL,
+  name TEXT NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  read_only BOOLEAN NOT NULL DEFAULT 0,
+  time_created INT NOT NULL,
+  time_last_used INT,
+  UNIQUE (username, name)
+);
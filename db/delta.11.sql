@@ -0,0 +1,8 @@
+CREATE TABLE sessions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username TEXT NOT NULL,
+  secret_hash TEXT NOT NULL UNIQUE,
+  user_agent TEXT,
+  time_created INT NOT NULL,
+  time_last_seen INT NOT NULL
+);
@@ -0,0 +1,8 @@
+CREATE TABLE login_failures (
+  kind TEXT NOT NULL,
+  key TEXT NOT NULL,
+  failure_count INT NOT NULL,
+  time_last_failure INT NOT NULL,
+  time_locked_until INT,
+  PRIMARY KEY (kind, key)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE password_resets (
+  token_hash TEXT PRIMARY KEY NOT NULL,
+  username TEXT NOT NULL,
+  time_created INT NOT NULL,
+  time_expires INT NOT NULL
+);
@@ -0,0 +1,10 @@
+CREATE TABLE invites (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  code_hash TEXT NOT NULL UNIQUE,
+  role TEXT NOT NULL DEFAULT 'member',
+  created_by TEXT,
+  time_created INT NOT NULL,
+  time_expires INT NOT NULL,
+  used_by TEXT,
+  time_used INT
+);
@@ -0,0 +1,11 @@
+CREATE TABLE totp (
+  username TEXT PRIMARY KEY NOT NULL,
+  secret_encrypted TEXT NOT NULL,
+  confirmed BOOLEAN NOT NULL DEFAULT 0,
+  last_used_step INT,
+  time_created INT NOT NULL
+);
+CREATE TABLE totp_recovery_codes (
+  code_hash TEXT PRIMARY KEY NOT NULL,
+  username TEXT NOT NULL
+);
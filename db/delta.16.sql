@@ -0,0 +1,12 @@
+CREATE TABLE audit_log (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  time_created INT NOT NULL,
+  username TEXT,
+  ip TEXT,
+  method TEXT NOT NULL,
+  path TEXT NOT NULL,
+  target TEXT,
+  status INT NOT NULL
+);
+CREATE INDEX audit_log_username ON audit_log (username);
+CREATE INDEX audit_log_target ON audit_log (target);
@@ -0,0 +1,24 @@
+CREATE TABLE readings (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username TEXT NOT NULL,
+  book_asin TEXT NOT NULL,
+  status TEXT NOT NULL,
+  start_date TEXT,
+  finish_date TEXT,
+  progress_percent INT,
+  progress_location INT,
+  time_created INT NOT NULL,
+  time_updated INT NOT NULL
+);
+CREATE INDEX readings_username_book_asin ON readings (username, book_asin);
+
+INSERT INTO readings (
+  username, book_asin, status, finish_date, progress_percent, time_created, time_updated
+)
+SELECT
+  username, book_asin, 'read', read_date, 100,
+  CAST(strftime('%s', 'now') AS INT) * 1000,
+  CAST(strftime('%s', 'now') AS INT) * 1000
+FROM read_state;
+
+DROP TABLE read_state;
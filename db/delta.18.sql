@@ -0,0 +1,8 @@
+CREATE TABLE book_ratings (
+  username TEXT NOT NULL,
+  book_asin TEXT NOT NULL,
+  rating INT,
+  note TEXT,
+  time_updated INT NOT NULL,
+  PRIMARY KEY (username, book_asin)
+);
@@ -0,0 +1,7 @@
+CREATE TABLE reading_goals (
+  username TEXT NOT NULL,
+  year INT NOT NULL,
+  target INT NOT NULL,
+  time_updated INT NOT NULL,
+  PRIMARY KEY (username, year)
+);
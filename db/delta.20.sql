@@ -0,0 +1,12 @@
+CREATE TABLE queue_preferences (
+  username TEXT PRIMARY KEY NOT NULL,
+  queue_order TEXT NOT NULL,
+  time_updated INT NOT NULL
+);
+
+CREATE TABLE queue_pins (
+  username TEXT NOT NULL,
+  series_asin TEXT NOT NULL,
+  position INT NOT NULL,
+  PRIMARY KEY (username, series_asin)
+);
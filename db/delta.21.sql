@@ -0,0 +1,3 @@
+-- from now on, each delta sets the schema version, so that backups can be checked
+-- before they are restored
+PRAGMA user_version = 21;
@@ -0,0 +1,216 @@
+-- Rebuilds every table that points at series, books or users, adding foreign keys,
+-- so that deleting any of them cleans up after itself. SQLite can't add constraints
+-- to existing tables, hence new table, copy, drop, rename for each one.
+--
+-- Rows are copied as they are, including orphans left behind by earlier deletes.
+-- Run `cargo run check-db --repair` afterwards to remove them.
+
+PRAGMA foreign_keys = OFF;
+
+BEGIN TRANSACTION;
+
+-- series_asin used to be INT, so numeric ASINs were stored as numbers and lost their
+-- leading zeros. Those are matched back to their series, ASINs are unique even without
+-- the zeros, as they all have the same length
+CREATE TABLE new_books (
+  asin TEXT NOT NULL UNIQUE PRIMARY KEY,
+  series_asin TEXT NOT NULL REFERENCES series (asin) ON DELETE CASCADE,
+  ordinal INT NOT NULL,
+  title TEXT NOT NULL,
+  author TEXT NOT NULL,
+  time_first_seen INT,
+  release_date TEXT
+);
+INSERT INTO new_books (asin, series_asin, ordinal, title, author, time_first_seen, release_date)
+SELECT
+  asin,
+  COALESCE(
+    (SELECT series.asin FROM series WHERE series.asin = CAST(books.series_asin AS TEXT)),
+    (
+      SELECT series.asin FROM series
+      WHERE typeof(books.series_asin) = 'integer'
+        AND ltrim(series.asin, '0') = CAST(books.series_asin AS TEXT)
+    ),
+    CAST(series_asin AS TEXT)
+  ),
+  ordinal, title, author, time_first_seen, release_date
+FROM books;
+DROP TABLE books;
+ALTER TABLE new_books RENAME TO books;
+CREATE INDEX books_series_asin ON books (series_asin);
+
+-- jobs and invites are shared history, they outlive the user who created them
+CREATE TABLE new_jobs (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  params TEXT NOT NULL,
+  status TEXT NOT NULL,
+  errors TEXT,
+  time_created INT NOT NULL,
+  time_started INT,
+  time_finished INT,
+  username TEXT REFERENCES credentials (username) ON DELETE SET NULL,
+  priority INT NOT NULL DEFAULT 1
+);
+INSERT INTO new_jobs SELECT
+  id, params, status, errors, time_created, time_started, time_finished, username, priority
+FROM jobs;
+DROP TABLE jobs;
+ALTER TABLE new_jobs RENAME TO jobs;
+
+CREATE TABLE new_invites (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  code_hash TEXT NOT NULL UNIQUE,
+  role TEXT NOT NULL DEFAULT 'member',
+  created_by TEXT REFERENCES credentials (username) ON DELETE SET NULL,
+  time_created INT NOT NULL,
+  time_expires INT NOT NULL,
+  used_by TEXT REFERENCES credentials (username) ON DELETE SET NULL,
+  time_used INT
+);
+INSERT INTO new_invites SELECT
+  id, code_hash, role, created_by, time_created, time_expires, used_by, time_used
+FROM invites;
+DROP TABLE invites;
+ALTER TABLE new_invites RENAME TO invites;
+
+CREATE TABLE new_subscriptions (
+  username TEXT NOT NULL REFERENCES credentials (username) ON DELETE CASCADE,
+  series_asin TEXT NOT NULL REFERENCES series (asin) ON DELETE CASCADE,
+  PRIMARY KEY (username, series_asin)
+);
+INSERT INTO new_subscriptions SELECT username, series_asin FROM subscriptions;
+DROP TABLE subscriptions;
+ALTER TABLE new_subscriptions RENAME TO subscriptions;
+
+CREATE TABLE new_readings (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username TEXT NOT NULL REFERENCES credentials (username) ON DELETE CASCADE,
+  book_asin TEXT NOT NULL REFERENCES books (asin) ON DELETE CASCADE,
+  status TEXT NOT NULL,
+  start_date TEXT,
+  finish_date TEXT,
+  progress_percent INT,
+  progress_location INT,
+  time_created INT NOT NULL,
+  time_updated INT NOT NULL
+);
+INSERT INTO new_readings SELECT
+  id, username, book_asin, status, start_date, finish_date, progress_percent,
+  progress_location, time_created, time_updated
+FROM readings;
+DROP TABLE readings;
+ALTER TABLE new_readings RENAME TO readings;
+CREATE INDEX readings_username_book_asin ON readings (username, book_asin);
+CREATE INDEX readings_book_asin ON readings (book_asin);
+
+CREATE TABLE new_book_ratings (
+  username TEXT NOT NULL REFERENCES credentials (username) ON DELETE CASCADE,
+  book_asin TEXT NOT NULL REFERENCES books (asin) ON DELETE CASCADE,
+  rating INT,
+  note TEXT,
+  time_updated INT NOT NULL,
+  PRIMARY KEY (username, book_asin)
+);
+INSERT INTO new_book_ratings SELECT username, book_asin, rating, note, time_updated
+FROM book_ratings;
+DROP TABLE book_ratings;
+ALTER TABLE new_book_ratings RENAME TO book_ratings;
+
+CREATE TABLE new_reading_goals (
+  username TEXT NOT NULL REFERENCES credentials (username) ON DELETE CASCADE,
+  year INT NOT NULL,
+  target INT NOT NULL,
+  time_updated INT NOT NULL,
+  PRIMARY KEY (username, year)
+);
+INSERT INTO new_reading_goals SELECT username, year, target, time_updated FROM reading_goals;
+DROP TABLE reading_goals;
+ALTER TABLE new_reading_goals RENAME TO reading_goals;
+
+CREATE TABLE new_queue_preferences (
+  username TEXT PRIMARY KEY NOT NULL REFERENCES credentials (username) ON DELETE CASCADE,
+  queue_order TEXT NOT NULL,
+  time_updated INT NOT NULL
+);
+INSERT INTO new_queue_preferences SELECT username, queue_order, time_updated
+FROM queue_preferences;
+DROP TABLE queue_preferences;
+ALTER TABLE new_queue_preferences RENAME TO queue_preferences;
+
+CREATE TABLE new_queue_pins (
+  username TEXT NOT NULL REFERENCES credentials (username) ON DELETE CASCADE,
+  series_asin TEXT NOT NULL REFERENCES series (asin) ON DELETE CASCADE,
+  position INT NOT NULL,
+  PRIMARY KEY (username, series_asin)
+);
+INSERT INTO new_queue_pins SELECT username, series_asin, position FROM queue_pins;
+DROP TABLE queue_pins;
+ALTER TABLE new_queue_pins RENAME TO queue_pins;
+
+CREATE TABLE new_api_tokens (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username TEXT NOT NULL REFERENCES credentials (username) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  token_hash TEXT NOT NULL UNIQUE,
+  read_only BOOLEAN NOT NULL DEFAULT 0,
+  time_created INT NOT NULL,
+  time_last_used INT,
+  UNIQUE (username, name)
+);
+INSERT INTO new_api_tokens SELECT
+  id, username, name, token_hash, read_only, time_created, time_last_used
+FROM api_tokens;
+DROP TABLE api_tokens;
+ALTER TABLE new_api_tokens RENAME TO api_tokens;
+
+CREATE TABLE new_sessions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  username TEXT NOT NULL REFERENCES credentials (username) ON DELETE CASCADE,
+  secret_hash TEXT NOT NULL UNIQUE,
+  user_agent TEXT,
+  time_created INT NOT NULL,
+  time_last_seen INT NOT NULL
+);
+INSERT INTO new_sessions SELECT
+  id, username, secret_hash, user_agent, time_created, time_last_seen
+FROM sessions;
+DROP TABLE sessions;
+ALTER TABLE new_sessions RENAME TO sessions;
+
+CREATE TABLE new_password_resets (
+  token_hash TEXT PRIMARY KEY NOT NULL,
+  username TEXT NOT NULL REFERENCES credentials (username) ON DELETE CASCADE,
+  time_created INT NOT NULL,
+  time_expires INT NOT NULL
+);
+INSERT INTO new_password_resets SELECT token_hash, username, time_created, time_expires
+FROM password_resets;
+DROP TABLE password_resets;
+ALTER TABLE new_password_resets RENAME TO password_resets;
+
+CREATE TABLE new_totp (
+  username TEXT PRIMARY KEY NOT NULL REFERENCES credentials (username) ON DELETE CASCADE,
+  secret_encrypted TEXT NOT NULL,
+  confirmed BOOLEAN NOT NULL DEFAULT 0,
+  last_used_step INT,
+  time_created INT NOT NULL
+);
+INSERT INTO new_totp SELECT
+  username, secret_encrypted, confirmed, last_used_step, time_created
+FROM totp;
+DROP TABLE totp;
+ALTER TABLE new_totp RENAME TO totp;
+
+CREATE TABLE new_totp_recovery_codes (
+  code_hash TEXT PRIMARY KEY NOT NULL,
+  username TEXT NOT NULL REFERENCES credentials (username) ON DELETE CASCADE
+);
+INSERT INTO new_totp_recovery_codes SELECT code_hash, username FROM totp_recovery_codes;
+DROP TABLE totp_recovery_codes;
+ALTER TABLE new_totp_recovery_codes RENAME TO totp_recovery_codes;
+
+PRAGMA user_version = 22;
+
+COMMIT;
+
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,5 @@
+ALTER TABLE credentials ADD COLUMN role TEXT NOT NULL DEFAULT 'member';
+
+-- Before roles existed every user could do everything, so keep it that way for
+-- existing accounts. New accounts are created as members.
+UPDATE credentials SET role = 'admin';
@@ -8,9 +8,10 @@ import {
   GetAllSeriesResult,
   GetAllJobsResult,
   Job,
+  JobEvent,
 } from "./generated/types";
 import { GetUserResult, User } from "./User";
-import { isJobProcessing } from "./Job";
+import { JobProgress, isJobFinishedStatus } from "./Job";
 import { BackendRoute, Route, RouteLink, usePathname } from "./Navigation";
 import { BooksPage } from "./BooksPage";
 import { SeriesPage } from "./SeriesPage";
@@ -47,12 +48,13 @@ function App() {
   const [books, setBooks] = useState<Array<Book>>([]);
   const [series, setSeries] = useState<Array<BookSeries>>([]);
   const [jobs, setJobs] = useState<Array<Job>>([]);
+  const [jobProgress, setJobProgress] = useState<JobProgress>({});
 
   const fetchHelper = new FetchHelper((_error) => {});
 
   const fetchUser = async () => {
     const onSuccess = (result: GetUserResult) =>
-      setUser(new User(result.username));
+      setUser(new User(result.username, result.role));
     const onFailure = () => setUser(new User(null));
 
     await fetchHelper.fetch<GetUserResult>(
@@ -84,21 +86,7 @@ function App() {
   const fetchJobs = async () => {
     await fetchHelper.fetch<GetAllJobsResult>(
       new Request(BackendRoute.Jobs),
-      (result) => {
-        const newJobs = result.jobs;
-
-        // on any job state transition to done, refetch series and books. Don't need
-        // to compare job by job, count of processing jobs is enough.
-        if (jobs != null) {
-          const oldProcessingCount = jobs.filter(isJobProcessing).length;
-          const newProcessingCount = newJobs.filter(isJobProcessing).length;
-          if (oldProcessingCount > newProcessingCount) {
-            fetchBooksAndSeries();
-          }
-        }
-
-        setJobs(newJobs);
-      },
+      (result) => setJobs(result.jobs),
     );
   };
 
@@ -113,23 +101,33 @@ function App() {
   }, [user]);
 
   useEffect(() => {
-    if (jobs.length === 0) {
+    if (user === null || !user.isLoggedIn()) {
       return;
     }
 
-    let intervalID = null;
-    const shouldRefresh = jobs.some(isJobProcessing);
-
-    if (shouldRefresh) {
-      intervalID = setInterval(fetchJobs, 5000);
-    }
-
-    return () => {
-      if (intervalID != null) {
-        clearInterval(intervalID);
+    // EventSource reconnects on its own, so there is no need for polling fallback
+    const eventSource = new EventSource(BackendRoute.JobEvents);
+    eventSource.onmessage = (message) => {
+      const event = JSON.parse(message.data) as JobEvent;
+      switch (event.variant) {
+        case "StatusChanged":
+          // once any job is done, its results should be visible in series and books
+          if (isJobFinishedStatus(event.status)) {
+            fetchBooksAndSeries();
+          }
+          fetchJobs();
+          break;
+        case "SeriesProgress":
+          setJobProgress((progress) => ({
+            ...progress,
+            [event.job_id]: event,
+          }));
+          break;
       }
     };
-  }, [jobs]);
+
+    return () => eventSource.close();
+  }, [user]);
 
   const userLoggedIn = user !== null && user.isLoggedIn();
 
@@ -159,13 +157,16 @@ function App() {
       case Route.Series:
         return (
           <SeriesPage
+            isAdmin={user.isAdmin()}
             series={series}
+            jobs={jobs}
+            jobProgress={jobProgress}
             refreshBooksAndSeries={fetchBooksAndSeries}
             refreshJobs={fetchJobs}
           />
         );
       case Route.Jobs:
-        return <JobsPage jobs={jobs} />;
+        return <JobsPage jobs={jobs} jobProgress={jobProgress} />;
       // break omitted: fallback to 404
       default:
         return <UI.PageNotFound />;
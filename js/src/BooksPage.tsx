@@ -1,6 +1,11 @@
 import React from "react";
 import { useState } from "react";
-import { Book, BookSeries } from "./generated/types";
+import {
+  Book,
+  BookSeries,
+  Reading,
+  ReadingStatus,
+} from "./generated/types";
 import { BackendRoute } from "./Navigation";
 import { AppSettings, useAppSettingsContext } from "./AppSettings";
 import { FetchHelper } from "./FetchHelper";
@@ -11,6 +16,90 @@ function sortByOrdinal(a: Book, b: Book) {
   return a.ordinal - b.ordinal;
 }
 
+const READING_STATUS_LABELS: Record<ReadingStatus, string> = {
+  want_to_read: "want to read",
+  reading: "reading",
+  read: "read",
+  abandoned: "abandoned",
+};
+
+// readings come newest first, so the first one is the current state of the book
+function getCurrentReading(book: Book): Reading | null {
+  return book.readings.length > 0 ? book.readings[0] : null;
+}
+
+function isRead(book: Book): boolean {
+  return getCurrentReading(book)?.status === "read";
+}
+
+function formatReading(reading: Reading): string {
+  switch (reading.status) {
+    case "reading":
+      return reading.progress_percent === null
+        ? "reading"
+        : `reading, ${reading.progress_percent}%`;
+    case "read":
+      return reading.finish_date ?? "read";
+    default:
+      return READING_STATUS_LABELS[reading.status];
+  }
+}
+
+type ReadingFormState = {
+  status: ReadingStatus;
+  start_date: string | null;
+  finish_date: string | null;
+  progress_percent: number | string;
+  progress_location: number | string;
+};
+
+function toFormState(reading: Reading | null): ReadingFormState {
+  return {
+    status: reading?.status ?? "read",
+    start_date: reading?.start_date ?? null,
+    finish_date: reading?.finish_date ?? null,
+    progress_percent: reading?.progress_percent ?? "",
+    progress_location: reading?.progress_location ?? "",
+  };
+}
+
+/* fields left empty are cleared, server fills in dates implied by the status */
+function toFormData(state: ReadingFormState): FormData {
+  const formData = new FormData();
+  formData.append("status", state.status);
+  if (state.start_date !== null) {
+    formData.append("start_date", state.start_date);
+  }
+  if (state.finish_date !== null) {
+    formData.append("finish_date", state.finish_date);
+  }
+  if (state.progress_percent !== "") {
+    formData.append("progress_percent", `${state.progress_percent}`);
+  }
+  if (state.progress_location !== "") {
+    formData.append("progress_location", `${state.progress_location}`);
+  }
+  return formData;
+}
+
+async function saveReading(
+  book: Book,
+  reading: Reading | null,
+  state: ReadingFormState,
+  onSuccess: () => void,
+) {
+  // without current reading, or when starting a re-read, new one is created
+  const url =
+    reading === null
+      ? `${BackendRoute.Books}/${book.asin}/readings`
+      : `${BackendRoute.Readings}/${reading.id}`;
+  const fetchHelper = FetchHelper.withAlert("Error while saving reading.");
+  await fetchHelper.fetch(
+    new Request(url, { method: "POST", body: toFormData(state) }),
+    (_result) => onSuccess(),
+  );
+}
+
 function MarkReadButton({
   book,
   refreshBooks,
@@ -18,15 +107,8 @@ function MarkReadButton({
   book: Book;
   refreshBooks: () => void;
 }) {
-  const markRead = async () => {
-    const url = `${BackendRoute.MarkRead}/${book.asin}`;
-    const fetchHelper = FetchHelper.withAlert(
-      "Error while marking book as read.",
-    );
-    await fetchHelper.fetch(new Request(url, { method: "POST" }), (_result) =>
-      refreshBooks(),
-    );
-  };
+  const markRead = () =>
+    saveReading(book, null, toFormState(null), refreshBooks);
 
   return (
     <UI.Button size="compact-sm" onClick={markRead}>
@@ -35,68 +117,241 @@ function MarkReadButton({
   );
 }
 
-function ReadDate({
+function ReadingModal({
   book,
+  reading,
+  opened,
+  close,
   refreshBooks,
 }: {
   book: Book;
+  reading: Reading | null;
+  opened: boolean;
+  close: () => void;
   refreshBooks: () => void;
 }) {
-  const [modalVisible, setModalVisible] = useState<boolean>(false);
-  const [readDate, setReadDate] = useState<string | null>(book.read_date);
+  const [state, setState] = useState<ReadingFormState>(toFormState(reading));
+
+  const onSaved = () => {
+    refreshBooks();
+    close();
+  };
+
+  const save = () => saveReading(book, reading, state, onSaved);
+
+  const readAgain = () =>
+    saveReading(
+      book,
+      null,
+      { ...toFormState(null), status: "reading" },
+      onSaved,
+    );
 
-  const updateReadDate = async () => {
-    if (readDate == book.read_date) {
-      setModalVisible(false);
+  const remove = async () => {
+    if (reading === null) {
       return;
     }
-    const url = `${BackendRoute.MarkRead}/${book.asin}/${readDate}`;
+    const url = `${BackendRoute.Readings}/${reading.id}`;
     const fetchHelper = FetchHelper.withAlert(
-      "Error while updating read date.",
+      "Error while removing reading.",
+    );
+    await fetchHelper.fetch(new Request(url, { method: "DELETE" }), (_result) =>
+      onSaved(),
     );
-    await fetchHelper.fetch(new Request(url, { method: "POST" }), (_result) => {
-      refreshBooks();
-      setModalVisible(false);
-    });
   };
 
-  const markUnread = async () => {
-    const url = `${BackendRoute.MarkUnread}/${book.asin}`;
-    const fetchHelper = FetchHelper.withAlert(
-      "Error while marking book as unread.",
-    );
-    await fetchHelper.fetch(new Request(url, { method: "POST" }), (_result) => {
+  const statusOptions = Object.entries(READING_STATUS_LABELS).map(
+    ([value, label]) => ({ value: value, label: label }),
+  );
+
+  return (
+    <UI.Modal opened={opened} onClose={close} title={book.title}>
+      <UI.Flex direction="column" gap="sm">
+        <UI.NativeSelect
+          label="Status"
+          data={statusOptions}
+          value={state.status}
+          onChange={(event) =>
+            setState({
+              ...state,
+              status: event.currentTarget.value as ReadingStatus,
+            })
+          }
+        />
+        <UI.DateInput
+          label="Started"
+          clearable
+          value={state.start_date}
+          onChange={(value) => setState({ ...state, start_date: value })}
+        />
+        <UI.DateInput
+          label="Finished"
+          clearable
+          value={state.finish_date}
+          onChange={(value) => setState({ ...state, finish_date: value })}
+        />
+        <UI.NumberInput
+          label="Progress %"
+          min={0}
+          max={100}
+          value={state.progress_percent}
+          onChange={(value) => setState({ ...state, progress_percent: value })}
+        />
+        <UI.NumberInput
+          label="Location"
+          min={0}
+          value={state.progress_location}
+          onChange={(value) =>
+            setState({ ...state, progress_location: value })
+          }
+        />
+      </UI.Flex>
+      <UI.Space h="md" />
+      <UI.Flex gap="xs">
+        {reading !== null && (
+          <UI.Button variant="outline" onClick={remove}>
+            remove
+          </UI.Button>
+        )}
+        {reading?.status === "read" && (
+          <UI.Button variant="outline" onClick={readAgain}>
+            read again
+          </UI.Button>
+        )}
+        <UI.Button ml="auto" onClick={save}>
+          save
+        </UI.Button>
+      </UI.Flex>
+    </UI.Modal>
+  );
+}
+
+function ReadingCell({
+  book,
+  refreshBooks,
+}: {
+  book: Book;
+  refreshBooks: () => void;
+}) {
+  const [modalVisible, setModalVisible] = useState<boolean>(false);
+  const reading = getCurrentReading(book);
+
+  return (
+    <>
+      <UI.Flex gap="0.2rem" align="center">
+        {reading === null ? (
+          <MarkReadButton book={book} refreshBooks={refreshBooks} />
+        ) : (
+          <UI.Text style={{ textWrap: "nowrap" }}>
+            {formatReading(reading)}
+          </UI.Text>
+        )}
+        <UI.CalendarButton onClick={() => setModalVisible(true)} />
+      </UI.Flex>
+
+      {modalVisible && (
+        <ReadingModal
+          book={book}
+          reading={reading}
+          opened={modalVisible}
+          close={() => setModalVisible(false)}
+          refreshBooks={refreshBooks}
+        />
+      )}
+    </>
+  );
+}
+
+async function saveRating(
+  book: Book,
+  rating: number | null,
+  note: string | null,
+  onSuccess: () => void,
+) {
+  const formData = new FormData();
+  if (rating !== null) {
+    formData.append("rating", `${rating}`);
+  }
+  if (note !== null) {
+    formData.append("note", note);
+  }
+
+  const url = `${BackendRoute.Books}/${book.asin}/rating`;
+  const fetchHelper = FetchHelper.withAlert("Error while saving rating.");
+  await fetchHelper.fetch(
+    new Request(url, { method: "POST", body: formData }),
+    (_result) => onSuccess(),
+  );
+}
+
+function NoteModal({
+  book,
+  close,
+  refreshBooks,
+}: {
+  book: Book;
+  close: () => void;
+  refreshBooks: () => void;
+}) {
+  const [note, setNote] = useState<string>(book.note ?? "");
+
+  const save = () =>
+    saveRating(book, book.rating, note, () => {
       refreshBooks();
-      setModalVisible(false);
+      close();
     });
-  };
+
+  return (
+    <UI.Modal opened={true} onClose={close} title={book.title}>
+      <UI.Textarea
+        label="Private notes"
+        autosize
+        minRows={4}
+        value={note}
+        onChange={(event) => setNote(event.currentTarget.value)}
+      />
+      <UI.Space h="md" />
+      <UI.Flex>
+        <UI.Button ml="auto" onClick={save}>
+          save
+        </UI.Button>
+      </UI.Flex>
+    </UI.Modal>
+  );
+}
+
+function RatingCell({
+  book,
+  refreshBooks,
+}: {
+  book: Book;
+  refreshBooks: () => void;
+}) {
+  const [modalVisible, setModalVisible] = useState<boolean>(false);
+
+  // clicking the current rating again clears it
+  const updateRating = (value: number) =>
+    saveRating(
+      book,
+      value === book.rating ? null : value,
+      book.note,
+      refreshBooks,
+    );
 
   return (
     <>
       <UI.Flex gap="0.2rem" align="center">
-        <UI.Text style={{ textWrap: "nowrap" }}>{book.read_date}</UI.Text>
-        <UI.CalendarButton onClick={() => setModalVisible(true)} />
+        <UI.Rating value={book.rating ?? 0} onChange={updateRating} />
+        <UI.NoteButton onClick={() => setModalVisible(true)} />
       </UI.Flex>
 
-      <UI.Modal
-        opened={modalVisible}
-        onClose={() => setModalVisible(false)}
-        title="Change read date"
-      >
-        <UI.Space h="sm" />
-        <UI.Center>
-          <UI.DatePicker value={readDate} onChange={setReadDate} />
-        </UI.Center>
-        <UI.Space h="md" />
-        <UI.Flex>
-          <UI.Button variant="outline" onClick={markUnread}>
-            mark unread
-          </UI.Button>
-          <UI.Button ml="auto" onClick={updateReadDate}>
-            save
-          </UI.Button>
-        </UI.Flex>
-      </UI.Modal>
+      {modalVisible && (
+        <NoteModal
+          book={book}
+          close={() => setModalVisible(false)}
+          refreshBooks={refreshBooks}
+        />
+      )}
     </>
   );
 }
@@ -131,14 +386,13 @@ function BookRow({
           <UI.Table.Td style={{ textWrap: "nowrap" }}>
             {book.release_date}
           </UI.Table.Td>
+          <UI.Table.Td>
+            <RatingCell book={book} refreshBooks={refreshBooks} />
+          </UI.Table.Td>
         </>
       )}
       <UI.Table.Td>
-        {book.read_date === null ? (
-          <MarkReadButton book={book} refreshBooks={refreshBooks} />
-        ) : (
-          <ReadDate book={book} refreshBooks={refreshBooks} />
-        )}
+        <ReadingCell book={book} refreshBooks={refreshBooks} />
       </UI.Table.Td>
     </UI.Table.Tr>
   );
@@ -184,7 +438,13 @@ function SeriesRows({
               </UI.Anchor>
             </UI.Table.Td>
 
-            <UI.Table.Td colSpan={2}></UI.Table.Td>
+            <UI.Table.Td></UI.Table.Td>
+            <UI.Table.Td>
+              {series.average_rating !== null && (
+                <UI.Text>avg {series.average_rating.toFixed(1)}</UI.Text>
+              )}
+            </UI.Table.Td>
+            <UI.Table.Td></UI.Table.Td>
           </>
         )}
       </UI.Table.Tr>
@@ -212,7 +472,7 @@ function isReleased(book: Book): boolean {
 }
 
 function includeBook(book: Book, settings: AppSettings) {
-  if (settings.hideReadBooks && book.read_date !== null) {
+  if (settings.hideReadBooks && isRead(book)) {
     return false;
   }
 
@@ -269,9 +529,10 @@ function BooksTable({
               )}
               <UI.Table.Th>ASIN</UI.Table.Th>
               <UI.Table.Th>Released</UI.Table.Th>
+              <UI.Table.Th>Rating</UI.Table.Th>
             </>
           )}
-          <UI.Table.Th w="1%">Read?</UI.Table.Th>
+          <UI.Table.Th w="1%">Reading</UI.Table.Th>
         </UI.Table.Tr>
       </UI.Table.Thead>
       <UI.Table.Tbody>
@@ -1,5 +1,26 @@
-import { Job } from "./generated/types";
+import { Job, JobEvent } from "./generated/types";
+
+export type SeriesProgressEvent = Extract<
+  JobEvent,
+  { variant: "SeriesProgress" }
+>;
+
+// keyed by job id, only holds jobs that reported any progress since page load
+export type JobProgress = Record<number, SeriesProgressEvent>;
 
 export function isJobProcessing(job: Job) {
   return ["QUEUED", "PROCESSING"].includes(job.status);
 }
+
+export function isJobFinishedStatus(status: string) {
+  return ["SUCCESSFUL", "FAILED"].includes(status);
+}
+
+export function formatJobStatus(job: Job, progress: JobProgress): string {
+  const jobProgress = progress[job.id];
+  if (job.status !== "PROCESSING" || jobProgress === undefined) {
+    return job.status;
+  }
+
+  return `${job.status} (${jobProgress.books_processed}/${jobProgress.books_total} books)`;
+}
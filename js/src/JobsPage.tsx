@@ -1,13 +1,69 @@
 import React from "react";
-import { Job } from "./generated/types";
+import { useState, useEffect } from "react";
+import { Job, JobServerHealth } from "./generated/types";
+import { BackendRoute } from "./Navigation";
+import { FetchHelper } from "./FetchHelper";
+import { JobProgress } from "./Job";
 import { JobsTable } from "./JobsTable";
 
 import * as UI from "./UI";
 
-export function JobsPage({ jobs }: { jobs: Array<Job> }) {
+function JobServerStatus() {
+  const [health, setHealth] = useState<JobServerHealth | null>(null);
+
+  const fetchHealth = async () => {
+    const fetchHelper = FetchHelper.withAlert(
+      "Error fetching job server status.",
+    );
+    await fetchHelper.fetch<JobServerHealth>(
+      new Request(BackendRoute.JobServer),
+      (result) => setHealth(result),
+    );
+  };
+
+  const restart = async () => {
+    const fetchHelper = FetchHelper.withAlert("Error restarting job server.");
+    await fetchHelper.fetch(
+      new Request(BackendRoute.JobServerRestart, { method: "POST" }),
+      (_result) => fetchHealth(),
+    );
+  };
+
+  useEffect(() => {
+    fetchHealth();
+  }, []);
+
+  if (health === null) {
+    return null;
+  }
+
+  return (
+    <UI.Flex gap="xs" align="center">
+      <UI.Text c={health.status === "Running" ? undefined : "red"}>
+        Job server: {health.status}
+        {health.status !== "Running" && ` (${health.reason})`}
+      </UI.Text>
+      <UI.ReloadButton onClick={fetchHealth} />
+      {health.status === "Stopped" && (
+        <UI.Button size="compact-sm" variant="outline" onClick={restart}>
+          restart
+        </UI.Button>
+      )}
+    </UI.Flex>
+  );
+}
+
+export function JobsPage({
+  jobs,
+  jobProgress,
+}: {
+  jobs: Array<Job>;
+  jobProgress: JobProgress;
+}) {
   return (
     <UI.Section title="Jobs">
-      <JobsTable jobs={jobs} />
+      <JobServerStatus />
+      <JobsTable jobs={jobs} jobProgress={jobProgress} />
     </UI.Section>
   );
 }
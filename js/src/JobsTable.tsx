@@ -1,6 +1,7 @@
 import React from "react";
 import { useState } from "react";
 import { Job } from "./generated/types";
+import { JobProgress, formatJobStatus } from "./Job";
 
 import * as UI from "./UI";
 
@@ -30,14 +31,15 @@ function Timestamp({ ts }: { ts: number | null }) {
   );
 }
 
-function JobStatus({ status }: { status: string }) {
-  if (status === "FAILED") {
+function JobStatus({ job, progress }: { job: Job; progress: JobProgress }) {
+  const status = formatJobStatus(job, progress);
+  if (job.status === "FAILED") {
     return <UI.Text c="red">{status}</UI.Text>;
   }
   return <UI.Text>{status}</UI.Text>;
 }
 
-function JobRow({ job }: { job: Job }) {
+function JobRow({ job, progress }: { job: Job; progress: JobProgress }) {
   const duration_s =
     job.time_started && job.time_finished
       ? (job.time_finished - job.time_started) / 1000
@@ -48,8 +50,9 @@ function JobRow({ job }: { job: Job }) {
       <UI.Table.Td>{job.id}</UI.Table.Td>
       <UI.Table.Td>{job.params}</UI.Table.Td>
       <UI.Table.Td>{job.username ?? <i>scheduled</i>}</UI.Table.Td>
+      <UI.Table.Td>{job.priority}</UI.Table.Td>
       <UI.Table.Td>
-        <JobStatus status={job.status} />
+        <JobStatus job={job} progress={progress} />
       </UI.Table.Td>
       <UI.Table.Td>
         <Timestamp ts={job.time_created} />
@@ -65,7 +68,13 @@ function JobRow({ job }: { job: Job }) {
 
 const JOBS_PER_PAGE = 100;
 
-export function JobsTable({ jobs }: { jobs: Array<Job> }) {
+export function JobsTable({
+  jobs,
+  jobProgress,
+}: {
+  jobs: Array<Job>;
+  jobProgress: JobProgress;
+}) {
   const [page, setPage] = useState<number>(1);
   const pageCount = Math.ceil(jobs.length / JOBS_PER_PAGE);
 
@@ -80,6 +89,7 @@ export function JobsTable({ jobs }: { jobs: Array<Job> }) {
             <UI.Table.Th>ID</UI.Table.Th>
             <UI.Table.Th>Params</UI.Table.Th>
             <UI.Table.Th>User</UI.Table.Th>
+            <UI.Table.Th>Priority</UI.Table.Th>
             <UI.Table.Th>Status</UI.Table.Th>
             <UI.Table.Th>Time Created</UI.Table.Th>
             <UI.Table.Th>Time Started</UI.Table.Th>
@@ -89,7 +99,7 @@ export function JobsTable({ jobs }: { jobs: Array<Job> }) {
         </UI.Table.Thead>
         <UI.Table.Tbody>
           {slice.map((job, index) => (
-            <JobRow key={index} job={job} />
+            <JobRow key={index} job={job} progress={jobProgress} />
           ))}
         </UI.Table.Tbody>
       </UI.Table>
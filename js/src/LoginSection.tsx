@@ -1,5 +1,6 @@
 import React from "react";
 import { useState } from "react";
+import { LoginResult } from "./generated/types";
 import { BackendRoute } from "./Navigation";
 import { User } from "./User";
 
@@ -23,6 +24,7 @@ function LoginError({ dismiss }: { dismiss: () => void }) {
 
 function LoginForm({ setUser }: { setUser: SetUserHandler }) {
   const [error, setError] = useState<boolean>(false);
+  const [totpRequired, setTotpRequired] = useState<boolean>(false);
 
   const onSubmit = async (e: React.SyntheticEvent) => {
     e.preventDefault();
@@ -30,7 +32,8 @@ function LoginForm({ setUser }: { setUser: SetUserHandler }) {
     try {
       const form = e.target as HTMLFormElement;
       const formData = new FormData(form);
-      const response = await fetch(BackendRoute.Login, {
+      const route = totpRequired ? BackendRoute.LoginTotp : BackendRoute.Login;
+      const response = await fetch(route, {
         method: "POST",
         body: formData,
       });
@@ -38,25 +41,44 @@ function LoginForm({ setUser }: { setUser: SetUserHandler }) {
         throw new Error();
       }
 
-      const result = await response.json();
-      const user = result as { username: string };
+      const result = (await response.json()) as LoginResult;
 
       setError(false);
-      setUser(new User(user.username));
+      switch (result.status) {
+        case "TotpRequired":
+          setTotpRequired(true);
+          break;
+        case "LoggedIn":
+          setUser(new User(result.user.username, result.user.role));
+          break;
+      }
     } catch (_error) {
       setError(true);
     }
   };
 
+  const fields = totpRequired ? (
+    <UI.TextInput
+      label="Code from authenticator app, or recovery code"
+      name="code"
+      autoComplete="one-time-code"
+      autoFocus
+    />
+  ) : (
+    <>
+      <UI.TextInput label="Username" name="username" />
+      <UI.PasswordInput label="Password" name="password" />
+    </>
+  );
+
   return (
     <UI.Flex direction="column" gap="md">
       {error && <LoginError dismiss={() => setError(false)} />}
       <form onSubmit={onSubmit}>
-        <UI.TextInput label="Username" name="username" />
-        <UI.PasswordInput label="Password" name="password" />
+        {fields}
         <UI.Space h="xl" />
         <UI.Button type="submit" fullWidth>
-          Log In
+          {totpRequired ? "Verify" : "Log In"}
         </UI.Button>
       </form>
     </UI.Flex>
@@ -6,17 +6,26 @@ import * as UI from "./UI";
 export enum BackendRoute {
   User = "/api/me",
   Books = "/api/books",
-  MarkRead = "/api/books/mark_read",
-  MarkUnread = "/api/books/mark_unread",
+  Readings = "/api/readings",
   Series = "/api/series",
   ScrapeAll = "/api/series/all",
   Subscribe = "/api/series/subscribe",
   Unsubscribe = "/api/series/unsubscribe",
   Jobs = "/api/jobs",
+  JobEvents = "/api/jobs/events",
+  JobServer = "/api/jobs/server",
+  JobServerRestart = "/api/jobs/server/restart",
   SkipSeries = "/api/series/skip",
   UnskipSeries = "/api/series/unskip",
+  Stats = "/api/stats",
+  Goals = "/api/goals",
+  Next = "/api/next",
+  Import = "/api/import",
+  AccountExport = "/api/account/export",
+  AccountImport = "/api/account/import",
 
   Login = "/api/login",
+  LoginTotp = "/api/login/totp",
   Logout = "/api/logout",
 }
 
@@ -1,6 +1,7 @@
 import React from "react";
 import { useState } from "react";
-import { BookSeries, AddSeriesResult } from "./generated/types";
+import { BookSeries, AddSeriesResult, Job } from "./generated/types";
+import { JobProgress, formatJobStatus } from "./Job";
 import { BackendRoute } from "./Navigation";
 import { FetchHelper } from "./FetchHelper";
 import { SeriesTable } from "./SeriesTable";
@@ -27,8 +28,37 @@ function ScrapeAllButton({ refreshJobs }: { refreshJobs: () => void }) {
   );
 }
 
-function AddSeriesForm({ refreshJobs }: { refreshJobs: () => void }) {
+function AddSeriesJobStatus({
+  jobId,
+  jobs,
+  jobProgress,
+}: {
+  jobId: number;
+  jobs: Array<Job>;
+  jobProgress: JobProgress;
+}) {
+  const job = jobs.find((job) => job.id === jobId);
+  const status =
+    job === undefined ? "QUEUED" : formatJobStatus(job, jobProgress);
+
+  return (
+    <UI.Text size="sm" c={job?.status === "FAILED" ? "red" : "dimmed"}>
+      Job {jobId}: {status}
+    </UI.Text>
+  );
+}
+
+function AddSeriesForm({
+  jobs,
+  jobProgress,
+  refreshJobs,
+}: {
+  jobs: Array<Job>;
+  jobProgress: JobProgress;
+  refreshJobs: () => void;
+}) {
   const [asin, setAsin] = useState<string>("");
+  const [jobId, setJobId] = useState<number | null>(null);
 
   const addSeries = async () => {
     const url = `${BackendRoute.Series}/${asin}`;
@@ -37,10 +67,8 @@ function AddSeriesForm({ refreshJobs }: { refreshJobs: () => void }) {
       new Request(url, { method: "POST" }),
       (result) => {
         setAsin("");
+        setJobId(result.job_id);
         refreshJobs();
-        alert(
-          `Submitted job ${result.job_id}. Go to Jobs tab to check progress.`,
-        );
       },
     );
   };
@@ -58,27 +86,45 @@ function AddSeriesForm({ refreshJobs }: { refreshJobs: () => void }) {
           add
         </UI.Button>
       </UI.Flex>
+      {jobId !== null && (
+        <AddSeriesJobStatus
+          jobId={jobId}
+          jobs={jobs}
+          jobProgress={jobProgress}
+        />
+      )}
     </UI.Flex>
   );
 }
 
 export function SeriesPage({
+  isAdmin,
   series,
+  jobs,
+  jobProgress,
   refreshBooksAndSeries,
   refreshJobs,
 }: {
+  isAdmin: boolean;
   series: Array<BookSeries>;
+  jobs: Array<Job>;
+  jobProgress: JobProgress;
   refreshBooksAndSeries: () => void;
   refreshJobs: () => void;
 }) {
   return (
     <UI.Section title="Tracked Series">
       <UI.Flex align="end">
-        <AddSeriesForm refreshJobs={refreshJobs} />
-        <ScrapeAllButton refreshJobs={refreshJobs} />
+        <AddSeriesForm
+          jobs={jobs}
+          jobProgress={jobProgress}
+          refreshJobs={refreshJobs}
+        />
+        {isAdmin && <ScrapeAllButton refreshJobs={refreshJobs} />}
       </UI.Flex>
       <UI.Space h="xs" />
       <SeriesTable
+        isAdmin={isAdmin}
         series={series}
         refreshSeries={refreshBooksAndSeries}
         refreshJobs={refreshJobs}
@@ -134,10 +134,12 @@ function DeleteButton({
 }
 
 function SeriesRow({
+  isAdmin,
   series,
   refreshSeries,
   refreshJobs,
 }: {
+  isAdmin: boolean;
   series: BookSeries;
   refreshSeries: () => void;
   refreshJobs: () => void;
@@ -173,8 +175,15 @@ function SeriesRow({
       <UI.Table.Td>
         <UI.Flex gap="xs">
           <RefreshButton series={series} refreshJobs={refreshJobs} />
-          <PauseScrapesButton series={series} refreshSeries={refreshSeries} />
-          <DeleteButton series={series} refreshSeries={refreshSeries} />
+          {isAdmin && (
+            <>
+              <PauseScrapesButton
+                series={series}
+                refreshSeries={refreshSeries}
+              />
+              <DeleteButton series={series} refreshSeries={refreshSeries} />
+            </>
+          )}
         </UI.Flex>
       </UI.Table.Td>
     </UI.Table.Tr>
@@ -182,10 +191,12 @@ function SeriesRow({
 }
 
 export function SeriesTable({
+  isAdmin,
   series,
   refreshSeries,
   refreshJobs,
 }: {
+  isAdmin: boolean;
   series: Array<BookSeries>;
   refreshSeries: () => void;
   refreshJobs: () => void;
@@ -209,6 +220,7 @@ export function SeriesTable({
         {series.map((item, index) => (
           <SeriesRow
             key={index}
+            isAdmin={isAdmin}
             series={item}
             refreshSeries={refreshSeries}
             refreshJobs={refreshJobs}
@@ -16,6 +16,7 @@ import {
   IconAdjustments,
   IconBooks,
   IconCalendarEvent,
+  IconNotes,
   IconPlayerPause,
   IconPlayerPlay,
   IconReload,
@@ -131,6 +132,14 @@ export function CalendarButton({ onClick }: { onClick: () => void }) {
   );
 }
 
+export function NoteButton({ onClick }: { onClick: () => void }) {
+  return (
+    <ActionIcon variant="subtle" size="sm" onClick={onClick}>
+      <IconNotes style={{ width: "70%", height: "70%" }} stroke={1.5} />
+    </ActionIcon>
+  );
+}
+
 export function ReloadButton({ onClick }: { onClick: () => void }) {
   return (
     <ActionIcon variant="subtle" size="sm" onClick={onClick}>
@@ -211,15 +220,18 @@ export {
   Flex,
   Modal,
   NativeSelect,
+  NumberInput,
   Pagination,
   PasswordInput,
+  Rating,
   Space,
   Switch,
   Table,
   Text,
+  Textarea,
   TextInput,
   Title,
   Tooltip,
 } from "@mantine/core";
-export { DatePicker } from "@mantine/dates";
+export { DateInput, DatePicker } from "@mantine/dates";
 export { IconAlertTriangle } from "@tabler/icons-react";
@@ -1,16 +1,24 @@
+import { Role } from "./generated/types";
+
 export class User {
   username: string | null;
+  role: Role | null;
 
-  constructor(username: string | null) {
+  constructor(username: string | null, role?: Role | null) {
     // can't trust undefined to not sneak in
     const sanitized = typeof username === "string" ? username : null;
     this.username = sanitized;
+    this.role = sanitized !== null ? (role ?? null) : null;
   }
 
   isLoggedIn() {
     return this.username !== null;
   }
 
+  isAdmin() {
+    return this.role === "admin";
+  }
+
   getName(): string {
     if (this.username === null) {
       throw new Error("Cannot request user name when user is not logged in!");
@@ -21,4 +29,5 @@ export class User {
 
 export type GetUserResult = {
   username: string;
+  role: Role;
 };
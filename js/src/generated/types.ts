@@ -2,14 +2,100 @@
 
 export type AddSeriesResult = { job_id: number, };
 
-export type Book = { read_date: string | null, asin: string, series_asin: string, ordinal: number, title: string, author: string, release_date: string | null, time_first_seen: number, };
+export type ApiToken = { id: number, name: string, read_only: boolean, time_created: number, time_last_used: number | null, };
 
-export type BookSeries = { count: number, subscribed: boolean, subscribers: number, asin: string, name: string, author: string, time_first_seen: bigint, skip_daily_scrape: boolean, };
+export type AuditLogEntry = { id: number, time_created: number, username: string | null, ip: string | null, method: string, path: string, target: string | null, status: number, };
+
+export type Book = { readings: Array<Reading>, rating: number | null, note: string | null, asin: string, series_asin: string, ordinal: number, title: string, author: string, release_date: string | null, time_first_seen: number, };
+
+export type BookSeries = { count: number, subscribed: boolean, subscribers: number, average_rating: number | null, asin: string, name: string, author: string, time_first_seen: bigint, skip_daily_scrape: boolean, };
+
+export type CreateInviteResult = { invite: Invite, code: string, };
+
+export type CreatePasswordResetResult = { username: string, token: string, time_expires: number, };
+
+export type CreateTokenResult = { token: ApiToken, secret: string, };
+
+export type ExportedGoal = { year: number, target: number, };
+
+export type ExportedRating = { book_asin: string, rating: number | null, note: string | null, };
+
+export type ExportedReading = { book_asin: string, status: ReadingStatus, start_date: string | null, finish_date: string | null, progress_percent: number | null, progress_location: number | null, };
 
 export type GetAllBooksResult = { books: Array<Book>, };
 
+export type GetAllGoalsResult = { goals: Array<GoalProgress>, };
+
+export type GetAllInvitesResult = { invites: Array<Invite>, };
+
 export type GetAllJobsResult = { jobs: Array<Job>, };
 
+export type GetAllPublicSeriesResult = { series: Array<PublicBookSeries>, };
+
 export type GetAllSeriesResult = { series: Array<BookSeries>, };
 
-export type Job = { id: number, params: string, status: string, errors: string | null, username: string | null, time_created: number, time_started: number | null, time_finished: number | null, };
+export type GetAllSessionsResult = { sessions: Array<Session>, };
+
+export type GetAllTokensResult = { tokens: Array<ApiToken>, };
+
+export type GetAllUsersResult = { users: Array<User>, };
+
+export type GetAuditLogResult = { entries: Array<AuditLogEntry>, };
+
+export type GetQueueResult = { order: QueueOrder, pinned: Array<string>, books: Array<QueuedBook>, };
+
+export type GetSeriesDetailResult = { series: PublicBookSeries, books: Array<PublicBook>, };
+
+export type GetStatsResult = { read_by_year: Array<PeriodCount>, read_by_month: Array<PeriodCount>, series_completion: Array<SeriesCompletion>, average_days_to_read: number | null, backlog: number, current_streak_months: number, longest_streak_months: number, };
+
+export type GetUpcomingBooksResult = { books: Array<PublicBook>, };
+
+export type GoalProgress = { year: number, target: number, read: number, remaining: number, expected_by_now: number, projected_finish_date: string | null, suggested_books: Array<PublicBook>, };
+
+export type ImportReport = { imported: number, skipped: number, unmatched: Array<UnmatchedRow>, };
+
+export type Invite = { id: number, role: Role, created_by: string | null, time_created: number, time_expires: number, used_by: string | null, time_used: number | null, };
+
+export type Job = { id: number, params: string, status: string, errors: string | null, username: string | null, priority: JobPriority, time_created: number, time_started: number | null, time_finished: number | null, };
+
+export type JobEvent = { "variant": "StatusChanged", job_id: number, status: string, } | { "variant": "SeriesProgress", job_id: number, series_asin: string, books_total: number, books_processed: number, };
+
+export type JobPriority = "Interactive" | "Scheduled" | "Backfill";
+
+export type JobServerHealth = { "status": "Running" } | { "status": "Degraded", reason: string, consecutive_failures: number, } | { "status": "Stopped", reason: string, };
+
+export type LoginResult = { "status": "LoggedIn", user: User, } | { "status": "TotpRequired" };
+
+export type PeriodCount = { period: string, count: number, };
+
+export type PublicBook = { asin: string, series_asin: string, ordinal: number, title: string, author: string, release_date: string | null, time_first_seen: number, };
+
+export type PublicBookSeries = { count: number, asin: string, name: string, author: string, time_first_seen: bigint, skip_daily_scrape: boolean, };
+
+export type QueueOrder = "oldest_unfinished" | "soonest_release" | "manual";
+
+export type QueuedBook = { unread_count: number, last_read_date: string | null, next_release_date: string | null, readings: Array<Reading>, rating: number | null, note: string | null, asin: string, series_asin: string, ordinal: number, title: string, author: string, release_date: string | null, time_first_seen: number, };
+
+export type Reading = { id: number, book_asin: string, status: ReadingStatus, start_date: string | null, finish_date: string | null, progress_percent: number | null, progress_location: number | null, time_created: number, time_updated: number, };
+
+export type ReadingStatus = "want_to_read" | "reading" | "read" | "abandoned";
+
+export type RecoveryCodesResult = { recovery_codes: Array<string>, };
+
+export type Role = "admin" | "member" | "read_only";
+
+export type SeriesCompletion = { series_asin: string, name: string, released: number, read: number, completion_percent: number | null, };
+
+export type Session = { current: boolean, id: number, user_agent: string | null, time_created: number, time_last_seen: number, };
+
+export type TotpEnrollment = { secret: string, provisioning_uri: string, };
+
+export type TotpStatus = { enabled: boolean, recovery_codes_left: number, };
+
+export type UnmatchedRow = { line: number, title: string, author: string, };
+
+export type User = { username: string, role: Role, };
+
+export type UserExport = { version: number, username: string, time_exported: number, subscriptions: Array<string>, readings: Array<ExportedReading>, ratings: Array<ExportedRating>, goals: Array<ExportedGoal>, queue_order: QueueOrder | null, queue_pinned: Array<string> | null, };
+
+export type UserImportReport = { added_subscriptions: number, added_readings: number, updated_ratings: number, updated_goals: number, unknown_series: Array<string>, unknown_books: Array<string>, };
@@ -0,0 +1,155 @@
+use rocket::form::FromForm;
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::common::{now, TS_FILE};
+use crate::database::Database;
+
+const DEFAULT_ENTRY_LIMIT: u32 = 100;
+const MAX_ENTRY_LIMIT: u32 = 1000;
+
+#[derive(sqlx::FromRow, Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct AuditLogEntry {
+    pub id: i32,
+    #[ts(as = "i32")]
+    pub time_created: i64,
+    pub username: Option<String>,
+    pub ip: Option<String>,
+    pub method: String,
+    pub path: String,
+    // dynamic part of the route, e.g. series ASIN or job id
+    pub target: Option<String>,
+    pub status: u16,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetAuditLogResult {
+    pub entries: Vec<AuditLogEntry>,
+}
+
+/* All filters are optional, entries are returned newest first */
+#[derive(FromForm, Debug)]
+pub struct AuditLogFilter {
+    pub username: Option<String>,
+    pub target: Option<String>,
+    pub method: Option<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+pub struct NewAuditLogEntry<'r> {
+    pub username: Option<&'r str>,
+    pub ip: Option<String>,
+    pub method: &'r str,
+    pub path: &'r str,
+    pub target: Option<String>,
+    pub status: u16,
+}
+
+impl AuditLogEntry {
+    pub async fn add(db: &Database, entry: NewAuditLogEntry<'_>) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let time_created = now();
+        sqlx::query!(
+            "INSERT INTO audit_log (time_created, username, ip, method, path, target, status)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            time_created,
+            entry.username,
+            entry.ip,
+            entry.method,
+            entry.path,
+            entry.target,
+            entry.status,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_filtered(
+        db: &Database,
+        filter: &AuditLogFilter,
+    ) -> anyhow::Result<GetAuditLogResult> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let limit = filter
+            .limit
+            .unwrap_or(DEFAULT_ENTRY_LIMIT)
+            .min(MAX_ENTRY_LIMIT);
+        let method = filter.method.as_ref().map(|value| value.to_uppercase());
+        let entries = sqlx::query_as::<_, AuditLogEntry>(
+            "SELECT id, time_created, username, ip, method, path, target, status
+            FROM audit_log
+            WHERE (?1 IS NULL OR username = ?1)
+                AND (?2 IS NULL OR target = ?2)
+                AND (?3 IS NULL OR method = ?3)
+                AND (?4 IS NULL OR time_created >= ?4)
+                AND (?5 IS NULL OR time_created < ?5)
+            ORDER BY time_created DESC, id DESC
+            LIMIT ?6",
+        )
+        .bind(&filter.username)
+        .bind(&filter.target)
+        .bind(method)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(GetAuditLogResult { entries: entries })
+    }
+}
+
+/* Picks values of dynamic segments out of request path, using the pattern of the
+route that handled it, e.g. "/api/series/<asin>" and "/api/series/B0123" give "B0123".
+Multiple values are joined with slash */
+pub fn get_route_target(route_pattern: &str, path: &str) -> Option<String> {
+    let route_path = match route_pattern.split_once('?') {
+        Some((route_path, _query)) => route_path,
+        None => route_pattern,
+    };
+
+    let values: Vec<&str> = route_path
+        .split('/')
+        .zip(path.split('/'))
+        .filter(|(pattern, _)| pattern.starts_with('<'))
+        .map(|(_, value)| value)
+        .collect();
+
+    match values.is_empty() {
+        true => None,
+        false => Some(values.join("/")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_route_target() {
+        assert_eq!(
+            get_route_target("/api/series/<asin>", "/api/series/B0123"),
+            Some(String::from("B0123"))
+        );
+        assert_eq!(
+            get_route_target("/api/books/<asin>/readings", "/api/books/B0123/readings"),
+            Some(String::from("B0123"))
+        );
+        assert_eq!(
+            get_route_target("/api/<kind>/<id>", "/api/readings/15"),
+            Some(String::from("readings/15"))
+        );
+        assert_eq!(
+            get_route_target("/api/series/all?<priority>", "/api/series/all"),
+            None
+        );
+        assert_eq!(get_route_target("/api/tokens", "/api/tokens"), None);
+    }
+}
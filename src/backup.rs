@@ -0,0 +1,160 @@
+use chrono::Local;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use sqlx::{ConnectOptions, Connection};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::database::{get_database_path, Database, SCHEMA_VERSION};
+
+const BACKUP_PREFIX: &str = "bst-";
+const BACKUP_EXTENSION: &str = ".db";
+
+#[derive(Clone, Debug)]
+pub struct BackupSettings {
+    pub dir: PathBuf,
+    // how many of the newest snapshots to keep, older ones are removed
+    pub keep: usize,
+}
+
+impl BackupSettings {
+    pub fn new(dir: PathBuf, keep: usize) -> anyhow::Result<BackupSettings> {
+        if keep == 0 {
+            return Err(anyhow::anyhow!("At least one backup has to be kept"));
+        }
+
+        Ok(BackupSettings {
+            dir: dir,
+            keep: keep,
+        })
+    }
+}
+
+/* Consistent snapshot even while the server is writing, unlike copying the file.
+It's written under temporary name first, so that half written snapshot never
+counts towards the rotation */
+pub async fn create_backup(db: &Database, settings: &BackupSettings) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(&settings.dir)?;
+
+    let filename = format!(
+        "{}{}{}",
+        BACKUP_PREFIX,
+        Local::now().format("%Y%m%d-%H%M%S"),
+        BACKUP_EXTENSION
+    );
+    let path = settings.dir.join(&filename);
+    let partial_path = settings.dir.join(format!("{}.partial", filename));
+    if partial_path.exists() {
+        fs::remove_file(&partial_path)?;
+    }
+
+    let mut conn = db.acquire_db_conn().await?;
+    sqlx::query("VACUUM INTO ?1")
+        .bind(partial_path.to_string_lossy().to_string())
+        .execute(&mut *conn)
+        .await?;
+    fs::rename(&partial_path, &path)?;
+
+    for expired in get_expired_backups(list_backups(&settings.dir)?, settings.keep) {
+        fs::remove_file(settings.dir.join(expired))?;
+    }
+
+    Ok(path)
+}
+
+fn list_backups(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_EXTENSION) {
+            names.push(name);
+        }
+    }
+
+    Ok(names)
+}
+
+/* Timestamps in names sort the same way as the time, so oldest come first */
+fn get_expired_backups(mut names: Vec<String>, keep: usize) -> Vec<String> {
+    names.sort();
+    let expired_count = names.len().saturating_sub(keep);
+    names.truncate(expired_count);
+    names
+}
+
+/* Checks the snapshot is intact and has the schema this build expects, before it
+replaces the database. Current database is kept next to it, just in case. Server
+must not be running */
+pub async fn restore_backup(backup_path: &Path) -> anyhow::Result<PathBuf> {
+    let mut conn = SqliteConnectOptions::new()
+        .filename(backup_path)
+        .read_only(true)
+        .connect()
+        .await?;
+    check_backup(&mut conn).await?;
+    conn.close().await?;
+
+    let database_path = get_database_path()?;
+    let previous_path = PathBuf::from(format!(
+        "{}.before-restore-{}",
+        database_path.display(),
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    let restoring_path = PathBuf::from(format!("{}.restoring", database_path.display()));
+
+    // copy first, so that failure half way through leaves the database as it was
+    fs::copy(backup_path, &restoring_path)?;
+
+    // journal belongs to the replaced database, it would be applied to the restored one
+    for suffix in ["", "-wal", "-shm"] {
+        let path = PathBuf::from(format!("{}{}", database_path.display(), suffix));
+        if path.exists() {
+            fs::rename(path, format!("{}{}", previous_path.display(), suffix))?;
+        }
+    }
+    fs::rename(&restoring_path, &database_path)?;
+
+    Ok(previous_path)
+}
+
+async fn check_backup(conn: &mut SqliteConnection) -> anyhow::Result<()> {
+    let integrity = sqlx::query_scalar::<_, String>("PRAGMA integrity_check")
+        .fetch_one(&mut *conn)
+        .await?;
+    if integrity != "ok" {
+        return Err(anyhow::anyhow!("Backup is corrupted: {}", integrity));
+    }
+
+    let version = sqlx::query_scalar::<_, i32>("PRAGMA user_version")
+        .fetch_one(&mut *conn)
+        .await?;
+    if version != SCHEMA_VERSION {
+        return Err(anyhow::anyhow!(
+            "Backup has schema version {}, but this server expects {}",
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_expired_backups() {
+        let names = vec![
+            String::from("bst-20250103-010000.db"),
+            String::from("bst-20250101-010000.db"),
+            String::from("bst-20250102-010000.db"),
+        ];
+
+        assert_eq!(
+            get_expired_backups(names.clone(), 2),
+            vec![String::from("bst-20250101-010000.db")]
+        );
+        assert_eq!(get_expired_backups(names.clone(), 3), Vec::<String>::new());
+        assert_eq!(get_expired_backups(names, 0).len(), 3);
+    }
+}
@@ -1,11 +1,15 @@
 use serde::Serialize;
+use std::collections::HashMap;
 use ts_rs::TS;
 
-use crate::common::TS_FILE;
+use crate::common::{today, TS_FILE};
 use crate::database::Database;
+use crate::ratings::BookRating;
+use crate::reads::Reading;
 use crate::user::User;
 
 #[derive(sqlx::FromRow, Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE, rename = "PublicBook")]
 pub struct Book {
     pub asin: String,
     pub series_asin: String,
@@ -17,20 +21,29 @@ pub struct Book {
     pub time_first_seen: i64,
 }
 
-#[derive(sqlx::FromRow, Serialize, TS, Debug)]
-#[ts(export_to = TS_FILE, rename="Book")]
-pub struct BookWithStatus {
+/* Readings are ordered newest first, so the first one is the current state of the
+book for the user. Empty if the user never touched the book */
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE, rename = "Book")]
+pub struct BookWithReadings {
     #[serde(flatten)]
-    #[sqlx(flatten)]
     #[ts(flatten)]
     pub book: Book,
-    pub read_date: Option<String>,
+    pub readings: Vec<Reading>,
+    pub rating: Option<u8>,
+    pub note: Option<String>,
 }
 
 #[derive(Serialize, TS, Debug)]
 #[ts(export_to = TS_FILE)]
 pub struct GetAllBooksResult {
-    pub books: Vec<BookWithStatus>,
+    pub books: Vec<BookWithReadings>,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetUpcomingBooksResult {
+    pub books: Vec<Book>,
 }
 
 impl Book {
@@ -81,27 +94,71 @@ impl Book {
         Ok(series)
     }
 
-    pub async fn fetch_by_user(db: &Database, user: &User) -> anyhow::Result<GetAllBooksResult> {
+    pub async fn fetch_all(db: &Database) -> anyhow::Result<Vec<Book>> {
         let mut conn = db.acquire_db_conn().await?;
+        let books = sqlx::query_as::<_, Book>("SELECT * FROM books")
+            .fetch_all(&mut *conn)
+            .await?;
+
+        Ok(books)
+    }
 
-        let books = sqlx::query_as::<_, BookWithStatus>(
-            "SELECT
-              books.*,
-              read_date
+    pub async fn fetch_by_user(db: &Database, user: &User) -> anyhow::Result<GetAllBooksResult> {
+        let mut readings_by_asin: HashMap<String, Vec<Reading>> = HashMap::new();
+        for reading in Reading::fetch_by_user(db, user).await? {
+            readings_by_asin
+                .entry(reading.book_asin.clone())
+                .or_default()
+                .push(reading);
+        }
+
+        let mut ratings_by_asin: HashMap<String, BookRating> = BookRating::fetch_by_user(db, user)
+            .await?
+            .into_iter()
+            .map(|rating| (rating.book_asin.clone(), rating))
+            .collect();
+
+        // acquired only after the fetches above, which take their own connections
+        let mut conn = db.acquire_db_conn().await?;
+        let books = sqlx::query_as::<_, Book>(
+            "SELECT books.*
             FROM books
             JOIN subscriptions USING (series_asin)
-            LEFT JOIN read_state ON (
-              books.asin = read_state.book_asin
-              AND subscriptions.username = read_state.username)
             WHERE subscriptions.username = ?1",
         )
         .bind(&user.username)
         .fetch_all(&mut *conn)
         .await?;
 
+        let books = books
+            .into_iter()
+            .map(|book| {
+                let rating = ratings_by_asin.remove(&book.asin);
+                BookWithReadings {
+                    readings: readings_by_asin.remove(&book.asin).unwrap_or_default(),
+                    rating: rating.as_ref().and_then(|rating| rating.rating),
+                    note: rating.and_then(|rating| rating.note),
+                    book: book,
+                }
+            })
+            .collect();
+
         Ok(GetAllBooksResult { books: books })
     }
 
+    /* Books released today or later, across all series, soonest first */
+    pub async fn fetch_upcoming(db: &Database) -> anyhow::Result<GetUpcomingBooksResult> {
+        let mut conn = db.acquire_db_conn().await?;
+        let books = sqlx::query_as::<_, Book>(
+            "SELECT * FROM books WHERE release_date >= ?1 ORDER BY release_date, title",
+        )
+        .bind(today())
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(GetUpcomingBooksResult { books: books })
+    }
+
     pub async fn fetch_by_series_asin(
         db: &Database,
         series_asin: &str,
@@ -1,3 +1,4 @@
+use chrono::Local;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::time::{sleep, Duration};
 
@@ -12,6 +13,11 @@ pub fn now() -> i64 {
         .unwrap()
 }
 
+/* Local date in the same format as book release dates, so they compare as strings */
+pub fn today() -> String {
+    Local::now().date_naive().format("%Y-%m-%d").to_string()
+}
+
 pub async fn sleep_seconds(seconds: u64) {
     sleep(Duration::new(seconds, 0)).await;
 }
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+
+use crate::database::Database;
+
+/* Row pointing at series, book or user that doesn't exist. Foreign keys prevent new
+ones, these are left from before they were added */
+#[derive(sqlx::FromRow, Debug)]
+struct Violation {
+    table: String,
+    rowid: Option<i64>,
+    parent: String,
+    fkid: i64,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+struct ForeignKey {
+    id: i64,
+    from: String,
+    on_delete: String,
+}
+
+async fn fetch_violations(db: &Database) -> anyhow::Result<Vec<Violation>> {
+    let mut conn = db.acquire_db_conn().await?;
+    let violations = sqlx::query_as::<_, Violation>(
+        "SELECT \"table\", rowid, parent, fkid FROM pragma_foreign_key_check",
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(violations)
+}
+
+async fn fetch_foreign_keys(db: &Database, table: &str) -> anyhow::Result<Vec<ForeignKey>> {
+    let mut conn = db.acquire_db_conn().await?;
+    let foreign_keys = sqlx::query_as::<_, ForeignKey>(
+        "SELECT id, \"from\", on_delete FROM pragma_foreign_key_list(?1)",
+    )
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(foreign_keys)
+}
+
+/* Does what the foreign key would have done, had it existed when the parent was
+deleted. Returns number of repaired rows */
+async fn repair(db: &Database, violations: &[Violation]) -> anyhow::Result<u64> {
+    let mut repaired = 0;
+    for violation in violations {
+        let rowid = match violation.rowid {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let foreign_key = match fetch_foreign_keys(db, &violation.table)
+            .await?
+            .into_iter()
+            .find(|foreign_key| foreign_key.id == violation.fkid)
+        {
+            Some(value) => value,
+            None => continue,
+        };
+
+        let query = get_repair_query(&violation.table, &foreign_key.from, &foreign_key.on_delete);
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query(&query).bind(rowid).execute(&mut *conn).await?;
+        repaired += result.rows_affected();
+    }
+
+    Ok(repaired)
+}
+
+fn get_repair_query(table: &str, column: &str, on_delete: &str) -> String {
+    match on_delete {
+        "SET NULL" => format!(
+            "UPDATE \"{}\" SET \"{}\" = NULL WHERE rowid = ?1",
+            table, column
+        ),
+        _ => format!("DELETE FROM \"{}\" WHERE rowid = ?1", table),
+    }
+}
+
+/* (table, missing parent) -> count, sorted so the report is stable */
+fn summarize(violations: &[Violation]) -> BTreeMap<(&str, &str), usize> {
+    let mut summary = BTreeMap::new();
+    for violation in violations {
+        *summary
+            .entry((violation.table.as_str(), violation.parent.as_str()))
+            .or_default() += 1;
+    }
+    summary
+}
+
+pub async fn check_database(db: Database, repair_orphans: bool) {
+    let violations = match fetch_violations(&db).await {
+        Ok(value) => value,
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    if violations.is_empty() {
+        println!("No orphaned rows found.");
+        return;
+    }
+
+    for ((table, parent), count) in summarize(&violations) {
+        println!("{}: {} rows pointing at missing {}", table, count, parent);
+    }
+
+    if !repair_orphans {
+        println!("Run with --repair to remove them.");
+        return;
+    }
+
+    match repair(&db, &violations).await {
+        Ok(count) => println!("Repaired {} rows.", count),
+        Err(e) => println!("Something went wrong: {}", e),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn violation(table: &str, parent: &str) -> Violation {
+        Violation {
+            table: table.to_string(),
+            rowid: Some(1),
+            parent: parent.to_string(),
+            fkid: 0,
+        }
+    }
+
+    #[test]
+    fn test_get_repair_query() {
+        assert_eq!(
+            get_repair_query("readings", "book_asin", "CASCADE"),
+            "DELETE FROM \"readings\" WHERE rowid = ?1"
+        );
+        assert_eq!(
+            get_repair_query("jobs", "username", "SET NULL"),
+            "UPDATE \"jobs\" SET \"username\" = NULL WHERE rowid = ?1"
+        );
+    }
+
+    #[test]
+    fn test_summarize() {
+        let violations = [
+            violation("subscriptions", "series"),
+            violation("readings", "books"),
+            violation("subscriptions", "credentials"),
+            violation("subscriptions", "series"),
+        ];
+
+        let summary: Vec<((&str, &str), usize)> = summarize(&violations).into_iter().collect();
+        assert_eq!(
+            summary,
+            vec![
+                (("readings", "books"), 1),
+                (("subscriptions", "credentials"), 1),
+                (("subscriptions", "series"), 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use rocket::form::{Form, FromForm};
+use rocket::http::CookieJar;
+use rocket::State;
+use serde::Serialize;
+use std::sync::Arc;
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+use crate::controllers::login::get_current_session;
+use crate::credentials::{check_password_strength, Credentials};
+use crate::crypto::{generate_token, hash_password, verify_password};
+use crate::database::Database;
+use crate::password_resets::PasswordReset;
+use crate::response::ApiResponse;
+use crate::sessions::Session;
+use crate::user::{Admin, Role, User};
+
+#[derive(FromForm)]
+pub struct ChangePasswordForm {
+    current_password: String,
+    new_password: String,
+}
+
+#[derive(FromForm)]
+pub struct ResetPasswordForm {
+    token: String,
+    new_password: String,
+}
+
+#[derive(FromForm)]
+pub struct CreateUserForm {
+    username: String,
+    role: Role,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetAllUsersResult {
+    pub users: Vec<User>,
+}
+
+#[post("/account/password", data = "<form>")]
+pub async fn change_password(
+    cookies: &CookieJar<'_>,
+    db: &State<Arc<Database>>,
+    user: &User,
+    form: Form<ChangePasswordForm>,
+) -> ApiResponse {
+    let mut creds = match Credentials::fetch_by_username(db, &user.username).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return ApiResponse::NotFound,
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    if verify_password(&creds.pwhash, &form.current_password).is_err() {
+        return ApiResponse::BadRequest {
+            message: String::from("Current password is incorrect!"),
+        };
+    }
+
+    if let Err(message) = set_password(db, &mut creds, &form.new_password).await {
+        return message;
+    }
+
+    // whoever knew the old password should not stay logged in, apart from this session
+    let current_id = match get_current_session(cookies, db).await {
+        Ok(session) => session.map(|session| session.id),
+        Err(error) => return ApiResponse::from_error(error),
+    };
+    match Session::delete_by_username(db, &user.username, current_id).await {
+        Ok(_) => ApiResponse::Success,
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+/* Only route here that does not need logged in user, token is the authorization */
+#[post("/account/reset", data = "<form>")]
+pub async fn reset_password(
+    db: &State<Arc<Database>>,
+    form: Form<ResetPasswordForm>,
+) -> ApiResponse {
+    let username = match PasswordReset::fetch_username(db, &form.token).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return reset_link_invalid(),
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    let pwhash = match hash_new_password(&username, &form.new_password) {
+        Ok(value) => value,
+        Err(message) => return message,
+    };
+
+    // token could have been used up or replaced in the meantime
+    let username = match PasswordReset::redeem(db, &form.token, &pwhash).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return reset_link_invalid(),
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    match Session::delete_by_username(db, &username, None).await {
+        Ok(_) => ApiResponse::Success,
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+fn reset_link_invalid() -> ApiResponse {
+    ApiResponse::BadRequest {
+        message: String::from("Reset link is invalid or expired!"),
+    }
+}
+
+#[get("/users")]
+pub async fn get_all_users(db: &State<Arc<Database>>, _admin: Admin<'_>) -> ApiResponse {
+    let users = match Credentials::fetch_all(db).await {
+        Ok(value) => value
+            .into_iter()
+            .map(|creds| User {
+                username: creds.username,
+                role: creds.role,
+            })
+            .collect(),
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    ApiResponse::from_object(GetAllUsersResult { users: users })
+}
+
+/* New user can't log in until they set the password using returned reset token */
+#[post("/users", data = "<form>")]
+pub async fn create_user(
+    db: &State<Arc<Database>>,
+    _admin: Admin<'_>,
+    form: Form<CreateUserForm>,
+) -> ApiResponse {
+    let username = form.username.trim();
+    if username.is_empty() {
+        return ApiResponse::BadRequest {
+            message: String::from("Username can't be empty!"),
+        };
+    }
+
+    match Credentials::fetch_by_username(db, username).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return ApiResponse::BadRequest {
+                message: String::from("User already exists!"),
+            }
+        }
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    let pwhash = match hash_password(&generate_token()) {
+        Ok(value) => value,
+        Err(_) => {
+            return ApiResponse::ServerError {
+                message: String::from("Hashing password failed."),
+            }
+        }
+    };
+
+    if let Err(error) = Credentials::create(db, username, &pwhash, form.role).await {
+        return ApiResponse::from_error(error);
+    }
+
+    match PasswordReset::create(db, username).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[post("/users/<username>/reset")]
+pub async fn create_password_reset(
+    db: &State<Arc<Database>>,
+    _admin: Admin<'_>,
+    username: &str,
+) -> ApiResponse {
+    match Credentials::fetch_by_username(db, username).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return ApiResponse::BadRequest {
+                message: String::from("User does not exist!"),
+            }
+        }
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    match PasswordReset::create(db, username).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[delete("/users/<username>")]
+pub async fn delete_user(
+    db: &State<Arc<Database>>,
+    admin: Admin<'_>,
+    username: &str,
+) -> ApiResponse {
+    if admin.0.username == username {
+        return ApiResponse::BadRequest {
+            message: String::from("Can't delete own account!"),
+        };
+    }
+
+    match Credentials::fetch_by_username(db, username).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return ApiResponse::BadRequest {
+                message: String::from("User does not exist!"),
+            }
+        }
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    match Credentials::delete_by_username(db, username).await {
+        Ok(_) => ApiResponse::Success,
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+async fn set_password(
+    db: &Database,
+    creds: &mut Credentials,
+    password: &str,
+) -> Result<(), ApiResponse> {
+    creds.pwhash = hash_new_password(&creds.username, password)?;
+
+    match creds.update(db).await {
+        Ok(_) => Ok(()),
+        Err(error) => Err(ApiResponse::from_error(error)),
+    }
+}
+
+fn hash_new_password(username: &str, password: &str) -> Result<String, ApiResponse> {
+    if let Err(message) = check_password_strength(username, password) {
+        return Err(ApiResponse::BadRequest { message: message });
+    }
+
+    match hash_password(password) {
+        Ok(value) => Ok(value),
+        Err(_) => Err(ApiResponse::ServerError {
+            message: String::from("Hashing password failed."),
+        }),
+    }
+}
@@ -0,0 +1,20 @@
+use rocket::State;
+use std::sync::Arc;
+
+use crate::audit_log::{AuditLogEntry, AuditLogFilter};
+use crate::database::Database;
+use crate::response::ApiResponse;
+use crate::user::Admin;
+
+/* E.g. /audit?target=<asin>&method=delete answers who removed the series */
+#[get("/audit?<filter..>")]
+pub async fn get_filtered(
+    db: &State<Arc<Database>>,
+    _admin: Admin<'_>,
+    filter: AuditLogFilter,
+) -> ApiResponse {
+    match AuditLogEntry::fetch_filtered(db, &filter).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
@@ -1,13 +1,66 @@
-use chrono::NaiveDate;
+use rocket::form::{Form, FromForm};
 use rocket::State;
 use std::sync::Arc;
 
 use crate::books::Book;
+use crate::common::today;
 use crate::database::Database;
-use crate::reads::ReadState;
+use crate::ratings::{check_rating, BookRating};
+use crate::reads::{Reading, ReadingParams, ReadingStatus};
 use crate::response::ApiResponse;
 use crate::user::User;
 
+/* Every field is set on each save, missing ones are cleared */
+#[derive(FromForm)]
+pub struct ReadingForm {
+    status: ReadingStatus,
+    start_date: Option<String>,
+    finish_date: Option<String>,
+    progress_percent: Option<u32>,
+    progress_location: Option<u32>,
+}
+
+impl ReadingForm {
+    fn to_params(&self) -> Result<ReadingParams, ApiResponse> {
+        let params = ReadingParams {
+            status: self.status,
+            start_date: non_empty(&self.start_date),
+            finish_date: non_empty(&self.finish_date),
+            progress_percent: self.progress_percent,
+            progress_location: self.progress_location,
+        };
+
+        params
+            .normalize(&today())
+            .map_err(|message| ApiResponse::BadRequest { message: message })
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<String> {
+    value
+        .as_ref()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/* Rating is taken as text, as Option<u8> would quietly turn invalid value into None
+and clear the rating */
+#[derive(FromForm)]
+pub struct RatingForm {
+    rating: Option<String>,
+    note: Option<String>,
+}
+
+fn parse_rating(value: &Option<String>) -> Result<Option<u8>, String> {
+    match non_empty(value) {
+        Some(value) => match value.parse::<u8>() {
+            Ok(rating) => Ok(Some(rating)),
+            Err(_) => Err(format!("'{}' is not a valid rating!", value)),
+        },
+        None => Ok(None),
+    }
+}
+
 #[get("/books")]
 pub async fn get_all(db: &State<Arc<Database>>, user: &User) -> ApiResponse {
     match Book::fetch_by_user(db, user).await {
@@ -16,77 +69,104 @@ pub async fn get_all(db: &State<Arc<Database>>, user: &User) -> ApiResponse {
     }
 }
 
-#[post("/books/mark_read/<asin>")]
-pub async fn mark_read(db: &State<Arc<Database>>, user: &User, asin: &str) -> ApiResponse {
+/* Starts new reading of the book, previous ones are kept as history of re-reads */
+#[post("/books/<asin>/readings", data = "<form>")]
+pub async fn add_reading(
+    db: &State<Arc<Database>>,
+    user: &User,
+    asin: &str,
+    form: Form<ReadingForm>,
+) -> ApiResponse {
+    let params = match form.to_params() {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
     if Book::fetch_by_asin(db, asin).await.is_err() {
         return ApiResponse::BadRequest {
             message: String::from("Book does not exist!"),
         };
     }
 
-    match ReadState::add(db, user, asin).await {
-        Ok(_) => ApiResponse::Success,
+    match Reading::create(db, user, asin, &params).await {
+        Ok(reading) => ApiResponse::from_object(reading),
         Err(error) => ApiResponse::from_error(error),
     }
 }
 
-#[post("/books/mark_read/<asin>/<date>")]
-pub async fn mark_read_on_date(
+#[post("/readings/<id>", data = "<form>")]
+pub async fn update_reading(
     db: &State<Arc<Database>>,
     user: &User,
-    asin: &str,
-    date: &str,
+    id: i32,
+    form: Form<ReadingForm>,
 ) -> ApiResponse {
-    if !is_valid_date(date) {
-        return ApiResponse::BadRequest {
-            message: String::from("Incorrect date format!"),
-        };
-    }
+    let params = match form.to_params() {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
 
-    if Book::fetch_by_asin(db, asin).await.is_err() {
-        return ApiResponse::BadRequest {
-            message: String::from("Book does not exist!"),
-        };
+    match Reading::update(db, user, id, &params).await {
+        Ok(Some(reading)) => ApiResponse::from_object(reading),
+        Ok(None) => ApiResponse::BadRequest {
+            message: String::from("Reading does not exist!"),
+        },
+        Err(error) => ApiResponse::from_error(error),
     }
+}
 
-    match ReadState::update(db, user, asin, date).await {
-        Ok(_) => ApiResponse::Success,
+#[delete("/readings/<id>")]
+pub async fn remove_reading(db: &State<Arc<Database>>, user: &User, id: i32) -> ApiResponse {
+    match Reading::delete(db, user, id).await {
+        Ok(true) => ApiResponse::Success,
+        Ok(false) => ApiResponse::BadRequest {
+            message: String::from("Reading does not exist!"),
+        },
         Err(error) => ApiResponse::from_error(error),
     }
 }
 
-#[post("/books/mark_unread/<asin>")]
-pub async fn mark_unread(db: &State<Arc<Database>>, user: &User, asin: &str) -> ApiResponse {
+/* Sets both rating and note, missing ones are cleared */
+#[post("/books/<asin>/rating", data = "<form>")]
+pub async fn set_rating(
+    db: &State<Arc<Database>>,
+    user: &User,
+    asin: &str,
+    form: Form<RatingForm>,
+) -> ApiResponse {
+    let rating = match parse_rating(&form.rating) {
+        Ok(value) => value,
+        Err(message) => return ApiResponse::BadRequest { message: message },
+    };
+    let note = non_empty(&form.note);
+    if let Err(message) = check_rating(rating, note.as_deref()) {
+        return ApiResponse::BadRequest { message: message };
+    }
+
     if Book::fetch_by_asin(db, asin).await.is_err() {
         return ApiResponse::BadRequest {
             message: String::from("Book does not exist!"),
         };
     }
 
-    match ReadState::remove(db, user, asin).await {
+    match BookRating::set(db, user, asin, rating, note.as_deref()).await {
         Ok(_) => ApiResponse::Success,
         Err(error) => ApiResponse::from_error(error),
     }
 }
 
-fn is_valid_date(date: &str) -> bool {
-    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
-        Ok(_) => true,
-        Err(_) => false,
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_is_valid_date() {
-        assert!(is_valid_date("2025-01-02"));
+    fn test_parse_rating() {
+        assert_eq!(parse_rating(&Some(String::from("4"))), Ok(Some(4)));
+        assert_eq!(parse_rating(&Some(String::from(" "))), Ok(None));
+        assert_eq!(parse_rating(&None), Ok(None));
 
-        assert!(!is_valid_date("2025-02-31"));
-        assert!(!is_valid_date(" 2025-01-02 "));
-        assert!(!is_valid_date("2025-01-02 some string"));
-        assert!(!is_valid_date("2025-01-02 09:15:46"));
+        assert!(parse_rating(&Some(String::from("abc"))).is_err());
+        assert!(parse_rating(&Some(String::from("-1"))).is_err());
+        assert!(parse_rating(&Some(String::from("300"))).is_err());
     }
 }
@@ -0,0 +1,53 @@
+use rocket::form::{Form, FromForm};
+use rocket::State;
+use std::sync::Arc;
+
+use crate::database::Database;
+use crate::goals::{self, check_target, check_year};
+use crate::response::ApiResponse;
+use crate::user::User;
+
+#[derive(FromForm)]
+pub struct GoalForm {
+    target: u32,
+}
+
+#[get("/goals")]
+pub async fn get_all(db: &State<Arc<Database>>, user: &User) -> ApiResponse {
+    match goals::fetch_by_user(db, user).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[post("/goals/<year>", data = "<form>")]
+pub async fn set(
+    db: &State<Arc<Database>>,
+    user: &User,
+    year: i32,
+    form: Form<GoalForm>,
+) -> ApiResponse {
+    if let Err(message) = check_target(form.target) {
+        return ApiResponse::BadRequest { message: message };
+    }
+
+    if let Err(message) = check_year(year) {
+        return ApiResponse::BadRequest { message: message };
+    }
+
+    match goals::set(db, user, year, form.target).await {
+        Ok(_) => ApiResponse::Success,
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[delete("/goals/<year>")]
+pub async fn remove(db: &State<Arc<Database>>, user: &User, year: i32) -> ApiResponse {
+    match goals::delete(db, user, year).await {
+        Ok(true) => ApiResponse::Success,
+        Ok(false) => ApiResponse::BadRequest {
+            message: String::from("Goal does not exist!"),
+        },
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
@@ -0,0 +1,36 @@
+use rocket::data::{Data, ToByteUnit};
+use rocket::State;
+use std::sync::Arc;
+
+use crate::database::Database;
+use crate::import::{import_rows, parse_csv};
+use crate::response::ApiResponse;
+use crate::user::User;
+
+/* Takes the exported CSV file as is, in request body */
+#[post("/import", data = "<data>")]
+pub async fn import(db: &State<Arc<Database>>, user: &User, data: Data<'_>) -> ApiResponse {
+    let content = match data.open(10.mebibytes()).into_string().await {
+        Ok(value) if value.is_complete() => value.into_inner(),
+        Ok(_) => {
+            return ApiResponse::BadRequest {
+                message: String::from("File is too large!"),
+            }
+        }
+        Err(error) => return ApiResponse::from_error(anyhow::anyhow!(error)),
+    };
+
+    let rows = match parse_csv(&content) {
+        Ok(value) => value,
+        Err(error) => {
+            return ApiResponse::BadRequest {
+                message: error.to_string(),
+            }
+        }
+    };
+
+    match import_rows(db, user, rows).await {
+        Ok(report) => ApiResponse::from_object(report),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
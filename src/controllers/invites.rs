@@ -0,0 +1,108 @@
+use rocket::form::{Form, FromForm};
+use rocket::State;
+use std::sync::Arc;
+
+use crate::credentials::{check_password_strength, Credentials};
+use crate::crypto::hash_password;
+use crate::database::Database;
+use crate::invites::{looks_like_username, Invite};
+use crate::response::ApiResponse;
+use crate::user::{Admin, Role};
+
+const DEFAULT_INVITE_VALID_DAYS: u32 = 7;
+const MAX_INVITE_VALID_DAYS: u32 = 90;
+
+#[derive(FromForm)]
+pub struct CreateInviteForm {
+    role: Role,
+    valid_days: Option<u32>,
+}
+
+#[derive(FromForm)]
+pub struct RegisterForm {
+    code: String,
+    username: String,
+    password: String,
+}
+
+#[get("/invites")]
+pub async fn get_all(db: &State<Arc<Database>>, _admin: Admin<'_>) -> ApiResponse {
+    match Invite::fetch_all(db).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[post("/invites", data = "<form>")]
+pub async fn create(
+    db: &State<Arc<Database>>,
+    admin: Admin<'_>,
+    form: Form<CreateInviteForm>,
+) -> ApiResponse {
+    let valid_days = form.valid_days.unwrap_or(DEFAULT_INVITE_VALID_DAYS);
+    if valid_days == 0 || valid_days > MAX_INVITE_VALID_DAYS {
+        return ApiResponse::BadRequest {
+            message: format!(
+                "Invite has to be valid for 1 to {} days!",
+                MAX_INVITE_VALID_DAYS
+            ),
+        };
+    }
+
+    match Invite::create(db, form.role, Some(&admin.0.username), valid_days).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[delete("/invites/<id>")]
+pub async fn remove(db: &State<Arc<Database>>, _admin: Admin<'_>, id: i32) -> ApiResponse {
+    match Invite::delete_by_id(db, id).await {
+        Ok(true) => ApiResponse::Success,
+        Ok(false) => ApiResponse::NotFound,
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+/* Public route, invite code is the authorization. Account gets the role that was
+chosen when the invite was created */
+#[post("/register", data = "<form>")]
+pub async fn register(db: &State<Arc<Database>>, form: Form<RegisterForm>) -> ApiResponse {
+    let username = form.username.trim();
+    if !looks_like_username(username) {
+        return ApiResponse::BadRequest {
+            message: String::from("Username can only have letters, digits, '.', '-' and '_'!"),
+        };
+    }
+
+    match Credentials::fetch_by_username(db, username).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            return ApiResponse::BadRequest {
+                message: String::from("User already exists!"),
+            }
+        }
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    if let Err(message) = check_password_strength(username, &form.password) {
+        return ApiResponse::BadRequest { message: message };
+    }
+
+    let pwhash = match hash_password(&form.password) {
+        Ok(value) => value,
+        Err(_) => {
+            return ApiResponse::ServerError {
+                message: String::from("Hashing password failed."),
+            }
+        }
+    };
+
+    match Invite::register(db, form.code.trim(), username, &pwhash).await {
+        Ok(Some(_)) => ApiResponse::Success,
+        Ok(None) => ApiResponse::BadRequest {
+            message: String::from("Invite is invalid, expired or already used!"),
+        },
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
@@ -1,9 +1,14 @@
-use rocket::State;
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{Shutdown, State};
 use std::sync::Arc;
 
 use crate::database::Database;
 use crate::response::ApiResponse;
 use crate::scraper::job::Job;
+use crate::scraper::server::JobServer;
+use crate::user::Admin;
 
 #[get("/jobs")]
 pub async fn get_all(db: &State<Arc<Database>>) -> ApiResponse {
@@ -12,3 +17,40 @@ pub async fn get_all(db: &State<Arc<Database>>) -> ApiResponse {
         Err(error) => ApiResponse::from_error(error),
     }
 }
+
+#[get("/jobs/server")]
+pub async fn get_server_health(job_server: &State<Arc<JobServer>>) -> ApiResponse {
+    ApiResponse::from_object(job_server.get_health())
+}
+
+#[post("/jobs/server/restart")]
+pub async fn restart_server(job_server: &State<Arc<JobServer>>, _admin: Admin<'_>) -> ApiResponse {
+    match JobServer::restart(job_server.inner().clone()) {
+        Ok(_) => ApiResponse::Success,
+        Err(error) => ApiResponse::BadRequest {
+            message: error.to_string(),
+        },
+    }
+}
+
+#[get("/jobs/events")]
+pub async fn events(job_server: &State<Arc<JobServer>>, mut shutdown: Shutdown) -> EventStream![] {
+    let mut receiver = job_server.events.subscribe();
+
+    EventStream! {
+        loop {
+            let event = select! {
+                message = receiver.recv() => match message {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    // client missed some events, but it will catch up on the next
+                    // status change, when UI refetches all jobs
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut shutdown => break,
+            };
+
+            yield Event::json(&event);
+        }
+    }
+}
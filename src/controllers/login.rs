@@ -1,20 +1,78 @@
 use rocket::form::{Form, FromForm};
 use rocket::http::{Cookie, CookieJar};
+use rocket::request::{FromRequest, Outcome, Request};
 use rocket::{post, State};
+use serde::Serialize;
+use std::convert::Infallible;
+use std::net::IpAddr;
 use std::sync::Arc;
+use ts_rs::TS;
 
+use crate::common::{now, sleep_seconds, TS_FILE};
 use crate::credentials::Credentials;
 use crate::crypto::verify_password;
 use crate::database::Database;
+use crate::login_failures::{FailureKind, LoginFailures};
+use crate::proxy_auth::ProxyAuthSettings;
 use crate::response::ApiResponse;
+use crate::sessions::{
+    GetAllSessionsResult, Session, SessionSettings, SessionWithStatus, SESSION_COOKIE,
+};
+use crate::totp::{Totp, TotpSettings};
 use crate::user::User;
 
+// how long the user has to enter 2FA code after giving correct password
+const LOGIN_CHALLENGE_TIMEOUT_MS: i64 = 5 * 60 * 1000;
+const LOGIN_CHALLENGE_COOKIE: &str = "login_challenge";
+
 #[derive(FromForm)]
 pub struct LoginForm {
     username: String,
     password: String,
 }
 
+#[derive(FromForm)]
+pub struct TotpLoginForm {
+    code: String,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[serde(tag = "status")]
+#[ts(export_to = TS_FILE)]
+pub enum LoginResult {
+    LoggedIn { user: User },
+    // password was correct, but session is only created after the second step
+    TotpRequired,
+}
+
+pub struct UserAgent<'r>(Option<&'r str>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserAgent<'r> {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(UserAgent(request.headers().get_one("User-Agent")))
+    }
+}
+
+/* Client address that can't be set by the client, see ProxyAuthSettings */
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientIp {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let ip = match request.rocket().state::<ProxyAuthSettings>() {
+            Some(settings) => settings.get_client_ip(request),
+            None => request.remote().map(|address| address.ip()),
+        };
+
+        Outcome::Success(ClientIp(ip))
+    }
+}
+
 #[get("/me")]
 pub async fn me(user: Option<&User>) -> ApiResponse {
     match user {
@@ -27,30 +85,342 @@ pub async fn me(user: Option<&User>) -> ApiResponse {
 pub async fn login(
     cookies: &CookieJar<'_>,
     db: &State<Arc<Database>>,
+    session_settings: &State<SessionSettings>,
+    proxy_auth: &State<ProxyAuthSettings>,
+    user_agent: UserAgent<'_>,
+    client_ip: ClientIp,
     form: Form<LoginForm>,
 ) -> ApiResponse {
+    if let Some(response) = reject_if_proxy_auth(proxy_auth) {
+        return response;
+    }
+
+    let ip = format_client_ip(client_ip.0);
+    if let Some(response) = check_lockout(db, &form.username, client_ip.0).await {
+        return response;
+    }
+
     let creds = match Credentials::fetch_by_username(db, &form.username).await {
+        Ok(Some(value)) => Some(value),
+        Ok(None) => None,
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    let creds = match creds {
+        Some(creds) if verify_password(&creds.pwhash, &form.password).is_ok() => creds,
+        _ => {
+            log::warn!("Failed login for '{}' from {}", form.username, ip);
+            return record_failure(db, &form.username, client_ip.0).await;
+        }
+    };
+
+    // failed logins are not cleared yet, so that guessing the code is slowed down too
+    match Totp::fetch_by_username(db, &creds.username).await {
+        Ok(Some(totp)) if totp.confirmed => {
+            let time_expires = now() + LOGIN_CHALLENGE_TIMEOUT_MS;
+            let challenge = format!("{}:{}", time_expires, creds.username);
+            cookies.add_private(Cookie::new(LOGIN_CHALLENGE_COOKIE, challenge));
+
+            return ApiResponse::from_object(LoginResult::TotpRequired);
+        }
+        Ok(_) => {}
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    start_session(cookies, db, session_settings, user_agent, creds).await
+}
+
+/* Second step of the login for users with 2FA enabled. Accepts either the code
+from authenticator app, or one of the recovery codes */
+#[post("/login/totp", data = "<form>")]
+pub async fn login_totp(
+    cookies: &CookieJar<'_>,
+    db: &State<Arc<Database>>,
+    session_settings: &State<SessionSettings>,
+    totp_settings: &State<TotpSettings>,
+    proxy_auth: &State<ProxyAuthSettings>,
+    user_agent: UserAgent<'_>,
+    client_ip: ClientIp,
+    form: Form<TotpLoginForm>,
+) -> ApiResponse {
+    if let Some(response) = reject_if_proxy_auth(proxy_auth) {
+        return response;
+    }
+
+    let username = match cookies
+        .get_private(LOGIN_CHALLENGE_COOKIE)
+        .and_then(|cookie| parse_login_challenge(cookie.value(), now()))
+    {
+        Some(value) => value,
+        None => {
+            return ApiResponse::BadRequest {
+                message: String::from("Login expired, log in with password again!"),
+            }
+        }
+    };
+
+    let ip = format_client_ip(client_ip.0);
+    if let Some(response) = check_lockout(db, &username, client_ip.0).await {
+        return response;
+    }
+
+    let creds = match Credentials::fetch_by_username(db, &username).await {
         Ok(Some(value)) => value,
-        _ => return ApiResponse::NotFound,
+        Ok(None) => return ApiResponse::NotFound,
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    let mut totp = match Totp::fetch_by_username(db, &username).await {
+        Ok(Some(value)) if value.confirmed => value,
+        // 2FA was reset in the meantime, password step has to be repeated
+        Ok(_) => {
+            cookies.remove_private(LOGIN_CHALLENGE_COOKIE);
+            return ApiResponse::BadRequest {
+                message: String::from("Login expired, log in with password again!"),
+            };
+        }
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    let is_valid = match totp.verify(db, totp_settings, &form.code).await {
+        Ok(true) => true,
+        Ok(false) => match Totp::use_recovery_code(db, &username, &form.code).await {
+            Ok(value) => value,
+            Err(error) => return ApiResponse::from_error(error),
+        },
+        Err(error) => return ApiResponse::from_error(error),
     };
 
-    if verify_password(&creds.pwhash, &form.password).is_err() {
-        return ApiResponse::NotFound;
+    if !is_valid {
+        log::warn!("Failed 2FA code for '{}' from {}", username, ip);
+        return record_failure(db, &username, client_ip.0).await;
+    }
+
+    cookies.remove_private(LOGIN_CHALLENGE_COOKIE);
+
+    start_session(cookies, db, session_settings, user_agent, creds).await
+}
+
+/* Proxy is the only place where users log in, password ones are not used anymore */
+fn reject_if_proxy_auth(proxy_auth: &ProxyAuthSettings) -> Option<ApiResponse> {
+    match proxy_auth.is_enabled() {
+        true => Some(ApiResponse::BadRequest {
+            message: String::from("Password login is disabled, log in through the proxy!"),
+        }),
+        false => None,
+    }
+}
+
+fn format_client_ip(client_ip: Option<IpAddr>) -> String {
+    match client_ip {
+        Some(value) => value.to_string(),
+        None => String::from("unknown"),
     }
+}
+
+/* Unknown addresses would all share the same key, so that one client could lock out
+all the others. Only username is tracked for them */
+fn get_failure_keys(username: &str, client_ip: Option<IpAddr>) -> Vec<(FailureKind, String)> {
+    let mut keys = vec![(FailureKind::Username, username.to_string())];
+    if let Some(ip) = client_ip {
+        keys.push((FailureKind::Ip, ip.to_string()));
+    }
+
+    keys
+}
 
-    let cookie = Cookie::new("user", creds.username.clone());
+/* Lockout applies even to correct password, otherwise it would not stop guessing */
+async fn check_lockout(
+    db: &Database,
+    username: &str,
+    client_ip: Option<IpAddr>,
+) -> Option<ApiResponse> {
+    for (kind, key) in get_failure_keys(username, client_ip) {
+        match LoginFailures::fetch(db, kind, &key).await {
+            Ok(Some(failures)) if failures.is_locked(now()) => {
+                log::warn!(
+                    "Rejected login for '{}' from {}, locked out",
+                    username,
+                    format_client_ip(client_ip)
+                );
+                return Some(ApiResponse::TooManyRequests {
+                    message: String::from("Too many failed login attempts, try again later."),
+                });
+            }
+            Ok(_) => {}
+            Err(error) => return Some(ApiResponse::from_error(error)),
+        };
+    }
+
+    None
+}
+
+async fn record_failure(db: &Database, username: &str, client_ip: Option<IpAddr>) -> ApiResponse {
+    let mut delay_seconds = 0;
+    for (kind, key) in get_failure_keys(username, client_ip) {
+        match LoginFailures::record(db, kind, &key).await {
+            Ok(delay) => delay_seconds = delay_seconds.max(delay),
+            Err(error) => return ApiResponse::from_error(error),
+        };
+    }
+    sleep_seconds(delay_seconds).await;
+
+    ApiResponse::NotFound
+}
+
+async fn start_session(
+    cookies: &CookieJar<'_>,
+    db: &Database,
+    session_settings: &SessionSettings,
+    user_agent: UserAgent<'_>,
+    creds: Credentials,
+) -> ApiResponse {
+    // address is not cleared, otherwise guessing other accounts could be interleaved
+    // with logins to a known one to avoid slowing down
+    if let Err(error) = LoginFailures::clear(db, FailureKind::Username, &creds.username).await {
+        log::warn!("Could not clear failed logins: {:?}", error);
+    }
+
+    // good moment to clean up, without having to run periodic job just for this
+    if let Err(error) = Session::delete_expired(db, session_settings).await {
+        log::warn!("Could not delete expired sessions: {:?}", error);
+    }
+
+    let secret = match Session::create(db, &creds.username, user_agent.0).await {
+        Ok(value) => value,
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    let cookie = Cookie::new(SESSION_COOKIE, secret);
     cookies.add_private(cookie);
 
     let user = User {
         username: creds.username,
+        role: creds.role,
     };
 
-    ApiResponse::from_object(user)
+    ApiResponse::from_object(LoginResult::LoggedIn { user: user })
+}
+
+/* Challenge is kept in private cookie, which can't be read or forged by the client,
+so there is no need to store pending logins on the server */
+fn parse_login_challenge(challenge: &str, time_now: i64) -> Option<String> {
+    let (time_expires, username) = challenge.split_once(':')?;
+    let time_expires = time_expires.parse::<i64>().ok()?;
+
+    match time_expires > time_now {
+        true => Some(username.to_string()),
+        false => None,
+    }
 }
 
 #[get("/logout")]
-pub async fn logout(cookies: &CookieJar<'_>) -> ApiResponse {
-    cookies.remove_private("user");
+pub async fn logout(cookies: &CookieJar<'_>, db: &State<Arc<Database>>) -> ApiResponse {
+    if let Ok(Some(session)) = get_current_session(cookies, db).await {
+        if let Err(error) = session.delete(db).await {
+            return ApiResponse::from_error(error);
+        }
+    }
+
+    cookies.remove_private(SESSION_COOKIE);
 
     ApiResponse::Success
 }
+
+#[get("/sessions")]
+pub async fn get_sessions(
+    cookies: &CookieJar<'_>,
+    db: &State<Arc<Database>>,
+    user: &User,
+) -> ApiResponse {
+    let current_id = match get_current_session(cookies, db).await {
+        Ok(session) => session.map(|session| session.id),
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    let sessions = match Session::fetch_by_username(db, &user.username).await {
+        Ok(value) => value,
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    let sessions = sessions
+        .into_iter()
+        .map(|session| SessionWithStatus {
+            current: Some(session.id) == current_id,
+            session: session,
+        })
+        .collect();
+
+    ApiResponse::from_object(GetAllSessionsResult { sessions: sessions })
+}
+
+#[delete("/sessions/<id>")]
+pub async fn revoke_session(db: &State<Arc<Database>>, user: &User, id: i32) -> ApiResponse {
+    match Session::delete_by_id(db, &user.username, id).await {
+        Ok(true) => ApiResponse::Success,
+        Ok(false) => ApiResponse::BadRequest {
+            message: String::from("Session does not exist!"),
+        },
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+/* Logs the user out on all other devices, keeping the session used for this request */
+#[delete("/sessions")]
+pub async fn revoke_other_sessions(
+    cookies: &CookieJar<'_>,
+    db: &State<Arc<Database>>,
+    user: &User,
+) -> ApiResponse {
+    let current_id = match get_current_session(cookies, db).await {
+        Ok(session) => session.map(|session| session.id),
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    match Session::delete_by_username(db, &user.username, current_id).await {
+        Ok(_) => ApiResponse::Success,
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+pub async fn get_current_session(
+    cookies: &CookieJar<'_>,
+    db: &Database,
+) -> anyhow::Result<Option<Session>> {
+    match cookies.get_private(SESSION_COOKIE) {
+        Some(cookie) => Session::fetch_by_secret(db, cookie.value()).await,
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_failure_keys() {
+        let keys = get_failure_keys("mom", Some("10.0.0.1".parse().unwrap()));
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[1].1, "10.0.0.1");
+
+        let keys = get_failure_keys("mom", None);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].1, "mom");
+    }
+
+    #[test]
+    fn test_parse_login_challenge() {
+        assert_eq!(
+            parse_login_challenge("2000:mom", 1000),
+            Some(String::from("mom"))
+        );
+        assert_eq!(
+            parse_login_challenge("2000:user:with:colons", 1000),
+            Some(String::from("user:with:colons"))
+        );
+
+        assert_eq!(parse_login_challenge("2000:mom", 2000), None);
+        assert_eq!(parse_login_challenge("mom", 1000), None);
+        assert_eq!(parse_login_challenge("soon:mom", 1000), None);
+    }
+}
@@ -1,5 +1,16 @@
+pub mod accounts;
+pub mod audit;
 pub mod books;
+pub mod goals;
+pub mod import;
 pub mod index;
+pub mod invites;
 pub mod jobs;
 pub mod login;
+pub mod public;
+pub mod queue;
 pub mod series;
+pub mod stats;
+pub mod tokens;
+pub mod totp;
+pub mod user_data;
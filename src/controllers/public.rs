@@ -0,0 +1,35 @@
+use rocket::State;
+use std::sync::Arc;
+
+use crate::books::Book;
+use crate::database::Database;
+use crate::response::ApiResponse;
+use crate::series::BookSeries;
+
+/* Routes here don't take User guard, GateKeeper decides whether they are open to
+visitors without login, depending on server config */
+
+#[get("/public/series")]
+pub async fn get_all_series(db: &State<Arc<Database>>) -> ApiResponse {
+    match BookSeries::fetch_all_public(db).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[get("/public/series/<asin>")]
+pub async fn get_series(db: &State<Arc<Database>>, asin: &str) -> ApiResponse {
+    match BookSeries::fetch_detail(db, asin).await {
+        Ok(Some(result)) => ApiResponse::from_object(result),
+        Ok(None) => ApiResponse::NotFound,
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[get("/public/upcoming")]
+pub async fn get_upcoming(db: &State<Arc<Database>>) -> ApiResponse {
+    match Book::fetch_upcoming(db).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
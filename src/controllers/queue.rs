@@ -0,0 +1,51 @@
+use rocket::form::{Form, FromForm};
+use rocket::State;
+use std::sync::Arc;
+
+use crate::database::Database;
+use crate::queue::{self, QueueOrder};
+use crate::response::ApiResponse;
+use crate::user::User;
+
+#[derive(FromForm)]
+pub struct OrderForm {
+    order: QueueOrder,
+}
+
+/* Series are sent as repeated field, in the wanted order */
+#[derive(FromForm)]
+pub struct PinnedForm {
+    series: Vec<String>,
+}
+
+#[get("/next")]
+pub async fn get_queue(db: &State<Arc<Database>>, user: &User) -> ApiResponse {
+    match queue::fetch_by_user(db, user).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[post("/next/order", data = "<form>")]
+pub async fn set_order(
+    db: &State<Arc<Database>>,
+    user: &User,
+    form: Form<OrderForm>,
+) -> ApiResponse {
+    match queue::set_order(db, user, form.order).await {
+        Ok(_) => ApiResponse::Success,
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[post("/next/pinned", data = "<form>")]
+pub async fn set_pinned(
+    db: &State<Arc<Database>>,
+    user: &User,
+    form: Form<PinnedForm>,
+) -> ApiResponse {
+    match queue::set_pinned(db, user, &form.series).await {
+        Ok(_) => ApiResponse::Success,
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
@@ -4,10 +4,11 @@ use std::sync::Arc;
 
 use crate::database::Database;
 use crate::response::ApiResponse;
-use crate::scraper::job::{Job, JobParams};
+use crate::scraper::job::{JobParams, JobPriority};
+use crate::scraper::server::JobServer;
 use crate::series::{AddSeriesResult, BookSeries};
 use crate::subscriptions::Subscription;
-use crate::user::User;
+use crate::user::{Admin, User};
 
 #[get("/series")]
 pub async fn get_all(db: &State<Arc<Database>>, user: &User) -> ApiResponse {
@@ -18,7 +19,7 @@ pub async fn get_all(db: &State<Arc<Database>>, user: &User) -> ApiResponse {
 }
 
 #[post("/series/<asin>")]
-pub async fn add(db: &State<Arc<Database>>, user: &User, asin: &str) -> ApiResponse {
+pub async fn add(job_server: &State<Arc<JobServer>>, user: &User, asin: &str) -> ApiResponse {
     if !looks_like_asin(asin) {
         return ApiResponse::BadRequest {
             message: format!("'{}' does not look like asin", asin),
@@ -28,14 +29,17 @@ pub async fn add(db: &State<Arc<Database>>, user: &User, asin: &str) -> ApiRespo
     let params = JobParams::Series {
         asin: asin.to_string(),
     };
-    match Job::add(db, params, Some(user)).await {
+    match job_server
+        .enqueue(params, JobPriority::Interactive, Some(&user.username))
+        .await
+    {
         Ok(job_id) => ApiResponse::from_object(AddSeriesResult { job_id: job_id }),
         Err(error) => ApiResponse::from_error(error),
     }
 }
 
 #[delete("/series/<asin>")]
-pub async fn remove(db: &State<Arc<Database>>, asin: &str) -> ApiResponse {
+pub async fn remove(db: &State<Arc<Database>>, _admin: Admin<'_>, asin: &str) -> ApiResponse {
     if BookSeries::fetch_by_asin(db, asin).await.is_err() {
         return ApiResponse::BadRequest {
             message: String::from("Series does not exist!"),
@@ -77,12 +81,12 @@ pub async fn unsubscribe(db: &State<Arc<Database>>, user: &User, asin: &str) ->
 }
 
 #[post("/series/skip/<asin>")]
-pub async fn skip(db: &State<Arc<Database>>, asin: &str) -> ApiResponse {
+pub async fn skip(db: &State<Arc<Database>>, _admin: Admin<'_>, asin: &str) -> ApiResponse {
     set_skip_daily_scrape(db, asin, true).await
 }
 
 #[post("/series/unskip/<asin>")]
-pub async fn unskip(db: &State<Arc<Database>>, asin: &str) -> ApiResponse {
+pub async fn unskip(db: &State<Arc<Database>>, _admin: Admin<'_>, asin: &str) -> ApiResponse {
     set_skip_daily_scrape(db, asin, false).await
 }
 
@@ -102,22 +106,31 @@ async fn set_skip_daily_scrape(db: &Database, asin: &str, skip: bool) -> ApiResp
     }
 }
 
-#[post("/series/all")]
-pub async fn scrape_all(db: &State<Arc<Database>>, user: &User) -> ApiResponse {
-    match enqueue_all_series(db, Some(user)).await {
+#[post("/series/all?<priority>")]
+pub async fn scrape_all(
+    job_server: &State<Arc<JobServer>>,
+    admin: Admin<'_>,
+    priority: Option<JobPriority>,
+) -> ApiResponse {
+    let priority = priority.unwrap_or(JobPriority::Scheduled);
+    match enqueue_all_series(job_server, priority, Some(&admin.0.username)).await {
         Ok(_) => ApiResponse::Success,
         Err(error) => ApiResponse::from_error(error),
     }
 }
 
-pub async fn enqueue_all_series(db: &Database, user: Option<&User>) -> anyhow::Result<()> {
-    let all_series = BookSeries::fetch_all(db).await?;
+pub async fn enqueue_all_series(
+    job_server: &JobServer,
+    priority: JobPriority,
+    username: Option<&str>,
+) -> anyhow::Result<()> {
+    let all_series = BookSeries::fetch_all(&job_server.database).await?;
     for series in all_series {
         if series.skip_daily_scrape {
             continue;
         }
         let params = JobParams::Series { asin: series.asin };
-        Job::add(db, params, user).await?;
+        job_server.enqueue(params, priority, username).await?;
     }
 
     Ok(())
@@ -0,0 +1,15 @@
+use rocket::State;
+use std::sync::Arc;
+
+use crate::database::Database;
+use crate::response::ApiResponse;
+use crate::stats;
+use crate::user::User;
+
+#[get("/stats")]
+pub async fn get_stats(db: &State<Arc<Database>>, user: &User) -> ApiResponse {
+    match stats::fetch_by_user(db, user).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
@@ -0,0 +1,54 @@
+use rocket::form::{Form, FromForm};
+use rocket::State;
+use std::sync::Arc;
+
+use crate::database::Database;
+use crate::response::ApiResponse;
+use crate::tokens::ApiToken;
+use crate::user::User;
+
+#[derive(FromForm)]
+pub struct CreateTokenForm {
+    name: String,
+    read_only: bool,
+}
+
+#[get("/tokens")]
+pub async fn get_all(db: &State<Arc<Database>>, user: &User) -> ApiResponse {
+    match ApiToken::fetch_by_username(db, &user.username).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[post("/tokens", data = "<form>")]
+pub async fn create(
+    db: &State<Arc<Database>>,
+    user: &User,
+    form: Form<CreateTokenForm>,
+) -> ApiResponse {
+    let name = form.name.trim();
+    if name.is_empty() {
+        return ApiResponse::BadRequest {
+            message: String::from("Token name can't be empty!"),
+        };
+    }
+
+    // token can't be used to mint tokens with more access than it has itself
+    let read_only = form.read_only || !user.can_write();
+    match ApiToken::create(db, &user.username, name, read_only).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[delete("/tokens/<name>")]
+pub async fn remove(db: &State<Arc<Database>>, user: &User, name: &str) -> ApiResponse {
+    match ApiToken::delete_by_name(db, &user.username, name).await {
+        Ok(true) => ApiResponse::Success,
+        Ok(false) => ApiResponse::BadRequest {
+            message: String::from("Token does not exist!"),
+        },
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
@@ -0,0 +1,159 @@
+use rocket::form::{Form, FromForm};
+use rocket::State;
+use std::sync::Arc;
+
+use crate::credentials::Credentials;
+use crate::crypto::verify_password;
+use crate::database::Database;
+use crate::response::ApiResponse;
+use crate::totp::{Totp, TotpSettings};
+use crate::user::User;
+
+#[derive(FromForm)]
+pub struct ConfirmTotpForm {
+    code: String,
+}
+
+#[derive(FromForm)]
+pub struct PasswordForm {
+    password: String,
+}
+
+#[get("/account/totp")]
+pub async fn get_status(db: &State<Arc<Database>>, user: &User) -> ApiResponse {
+    match Totp::get_status(db, &user.username).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+/* Starts enrollment, 2FA is not required on login until it's confirmed with a code
+from the app. Calling it again before confirming starts over with new secret */
+#[post("/account/totp")]
+pub async fn enroll(
+    db: &State<Arc<Database>>,
+    totp_settings: &State<TotpSettings>,
+    user: &User,
+) -> ApiResponse {
+    if totp_settings.get_key().is_err() {
+        return ApiResponse::BadRequest {
+            message: String::from("2FA is not configured on this server!"),
+        };
+    }
+
+    match Totp::fetch_by_username(db, &user.username).await {
+        Ok(Some(totp)) if totp.confirmed => {
+            return ApiResponse::BadRequest {
+                message: String::from("2FA is already enabled!"),
+            }
+        }
+        Ok(_) => {}
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    match Totp::begin_enrollment(db, totp_settings, &user.username).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+/* Enables 2FA and returns recovery codes, which are not shown again */
+#[post("/account/totp/confirm", data = "<form>")]
+pub async fn confirm(
+    db: &State<Arc<Database>>,
+    totp_settings: &State<TotpSettings>,
+    user: &User,
+    form: Form<ConfirmTotpForm>,
+) -> ApiResponse {
+    let mut totp = match Totp::fetch_by_username(db, &user.username).await {
+        Ok(Some(value)) if !value.confirmed => value,
+        Ok(Some(_)) => {
+            return ApiResponse::BadRequest {
+                message: String::from("2FA is already enabled!"),
+            }
+        }
+        Ok(None) => {
+            return ApiResponse::BadRequest {
+                message: String::from("2FA enrollment was not started!"),
+            }
+        }
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    match totp.verify(db, totp_settings, &form.code).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return ApiResponse::BadRequest {
+                message: String::from("Code is not valid, check the time on your device!"),
+            }
+        }
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    match Totp::create_recovery_codes(db, &user.username).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+#[post("/account/totp/recovery_codes", data = "<form>")]
+pub async fn regenerate_recovery_codes(
+    db: &State<Arc<Database>>,
+    user: &User,
+    form: Form<PasswordForm>,
+) -> ApiResponse {
+    if let Err(response) = check_password(db, user, &form.password).await {
+        return response;
+    }
+
+    match Totp::fetch_by_username(db, &user.username).await {
+        Ok(Some(totp)) if totp.confirmed => {}
+        Ok(_) => {
+            return ApiResponse::BadRequest {
+                message: String::from("2FA is not enabled!"),
+            }
+        }
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    match Totp::create_recovery_codes(db, &user.username).await {
+        Ok(result) => ApiResponse::from_object(result),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+/* Password is required, so that unattended logged in browser is not enough to
+weaken the account */
+#[delete("/account/totp", data = "<form>")]
+pub async fn disable(
+    db: &State<Arc<Database>>,
+    user: &User,
+    form: Form<PasswordForm>,
+) -> ApiResponse {
+    if let Err(response) = check_password(db, user, &form.password).await {
+        return response;
+    }
+
+    match Totp::delete_by_username(db, &user.username).await {
+        Ok(true) => ApiResponse::Success,
+        Ok(false) => ApiResponse::BadRequest {
+            message: String::from("2FA is not enabled!"),
+        },
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+async fn check_password(db: &Database, user: &User, password: &str) -> Result<(), ApiResponse> {
+    let creds = match Credentials::fetch_by_username(db, &user.username).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return Err(ApiResponse::NotFound),
+        Err(error) => return Err(ApiResponse::from_error(error)),
+    };
+
+    match verify_password(&creds.pwhash, password) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ApiResponse::BadRequest {
+            message: String::from("Password is incorrect!"),
+        }),
+    }
+}
@@ -0,0 +1,88 @@
+use rocket::data::{Data, ToByteUnit};
+use rocket::form::FromFormField;
+use rocket::http::ContentType;
+use rocket::State;
+use std::sync::Arc;
+
+use crate::common::today;
+use crate::database::Database;
+use crate::response::{ApiResponse, Attachment};
+use crate::user::User;
+use crate::user_data::{self, check_export, from_csv, to_csv, UserExport};
+
+#[derive(FromFormField, Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    #[field(value = "json")]
+    Json,
+    #[field(value = "csv")]
+    Csv,
+}
+
+#[get("/account/export?<format>")]
+pub async fn export(
+    db: &State<Arc<Database>>,
+    user: &User,
+    format: Option<ExportFormat>,
+) -> ApiResponse {
+    let data = match user_data::export(db, user).await {
+        Ok(value) => value,
+        Err(error) => return ApiResponse::from_error(error),
+    };
+
+    let format = format.unwrap_or(ExportFormat::Json);
+    let serialized = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&data).map_err(anyhow::Error::from),
+        ExportFormat::Csv => to_csv(&data),
+    };
+
+    let (content_type, extension) = match format {
+        ExportFormat::Json => (ContentType::JSON, "json"),
+        ExportFormat::Csv => (ContentType::CSV, "csv"),
+    };
+
+    match serialized {
+        Ok(value) => ApiResponse::Attachment(Attachment {
+            content_type: content_type,
+            filename: format!("bst-{}-{}.{}", user.username, today(), extension),
+            data: value,
+        }),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
+
+/* Takes either of the export files as is, in request body */
+#[post("/account/import", data = "<data>")]
+pub async fn import(db: &State<Arc<Database>>, user: &User, data: Data<'_>) -> ApiResponse {
+    let content = match data.open(10.mebibytes()).into_string().await {
+        Ok(value) if value.is_complete() => value.into_inner(),
+        Ok(_) => {
+            return ApiResponse::BadRequest {
+                message: String::from("File is too large!"),
+            }
+        }
+        Err(error) => return ApiResponse::from_error(anyhow::anyhow!(error)),
+    };
+
+    let parsed = match content.trim_start().starts_with('{') {
+        true => serde_json::from_str::<UserExport>(&content).map_err(anyhow::Error::from),
+        false => from_csv(&content, &user.username),
+    };
+
+    let export = match parsed {
+        Ok(value) => value,
+        Err(error) => {
+            return ApiResponse::BadRequest {
+                message: format!("Can't read the export: {}", error),
+            }
+        }
+    };
+
+    if let Err(message) = check_export(&export) {
+        return ApiResponse::BadRequest { message: message };
+    }
+
+    match user_data::import(db, user, export).await {
+        Ok(report) => ApiResponse::from_object(report),
+        Err(error) => ApiResponse::from_error(error),
+    }
+}
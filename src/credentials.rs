@@ -1,21 +1,31 @@
-use sqlx::FromRow;
+use sqlx::{Connection, FromRow};
 
 use crate::database::Database;
+use crate::user::Role;
+
+const MIN_PASSWORD_LENGTH: usize = 10;
 
 #[derive(Debug, FromRow)]
 pub struct Credentials {
     pub username: String,
     pub pwhash: String,
+    pub role: Role,
 }
 
 #[allow(dead_code)]
 impl Credentials {
-    pub async fn create(db: &Database, username: &str, pwhash: &str) -> anyhow::Result<()> {
+    pub async fn create(
+        db: &Database,
+        username: &str,
+        pwhash: &str,
+        role: Role,
+    ) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
         sqlx::query!(
-            "INSERT INTO credentials (username, pwhash) VALUES (?1, ?2)",
+            "INSERT INTO credentials (username, pwhash, role) VALUES (?1, ?2, ?3)",
             username,
             pwhash,
+            role,
         )
         .execute(&mut *conn)
         .await?;
@@ -23,19 +33,33 @@ impl Credentials {
         Ok(())
     }
 
+    /* Removes the user together with everything that belongs to them, the rest is
+    done by foreign keys. Jobs and invites are kept, as they are shared history rather
+    than user data. Login failures are keyed by either username or IP, so they have no
+    foreign key */
     pub async fn delete_by_username(db: &Database, username: &str) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM login_failures WHERE kind = 'username' AND key = ?1",
+            username
+        )
+        .execute(&mut *tx)
+        .await?;
         sqlx::query!("DELETE FROM credentials WHERE username = ?1", username)
-            .execute(&mut *conn)
+            .execute(&mut *tx)
             .await?;
 
+        tx.commit().await?;
+
         Ok(())
     }
 
     pub async fn fetch_all(db: &Database) -> anyhow::Result<Vec<Credentials>> {
         let mut conn = db.acquire_db_conn().await?;
         let result = sqlx::query_as::<_, Credentials>(
-            "SELECT username, pwhash FROM credentials ORDER BY username",
+            "SELECT username, pwhash, role FROM credentials ORDER BY username",
         )
         .fetch_all(&mut *conn)
         .await?;
@@ -49,7 +73,7 @@ impl Credentials {
     ) -> anyhow::Result<Option<Credentials>> {
         let mut conn = db.acquire_db_conn().await?;
         let result = sqlx::query_as::<_, Credentials>(
-            "SELECT username, pwhash FROM credentials WHERE username = ?1",
+            "SELECT username, pwhash, role FROM credentials WHERE username = ?1",
         )
         .bind(username)
         .fetch_optional(&mut *conn)
@@ -61,9 +85,10 @@ impl Credentials {
     pub async fn update(&self, db: &Database) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
         sqlx::query!(
-            "UPDATE credentials SET pwhash = ?2 WHERE username = ?1",
+            "UPDATE credentials SET pwhash = ?2, role = ?3 WHERE username = ?1",
             self.username,
             self.pwhash,
+            self.role,
         )
         .execute(&mut *conn)
         .await?;
@@ -71,3 +96,41 @@ impl Credentials {
         Ok(())
     }
 }
+
+/* Returns reason for rejecting the password, if any. Kept deliberately simple,
+length matters way more than mixing character classes */
+pub fn check_password_strength(username: &str, password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!(
+            "Password must be at least {} characters long.",
+            MIN_PASSWORD_LENGTH
+        ));
+    }
+
+    if password.to_lowercase().contains(&username.to_lowercase()) {
+        return Err(String::from("Password can't contain the username."));
+    }
+
+    let first = password.chars().next();
+    if password.chars().all(|c| Some(c) == first) {
+        return Err(String::from(
+            "Password can't be a single repeated character.",
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_password_strength() {
+        assert!(check_password_strength("user", "correct horse battery").is_ok());
+
+        assert!(check_password_strength("user", "short").is_err());
+        assert!(check_password_strength("user", "my_USER_password").is_err());
+        assert!(check_password_strength("user", "aaaaaaaaaaaaaa").is_err());
+    }
+}
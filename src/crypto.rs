@@ -3,6 +3,11 @@ use libsodium_sys as ffi;
 const STRBYTES: usize = ffi::crypto_pwhash_STRBYTES as usize;
 const OPSLIMIT_INTERACTIVE: u64 = ffi::crypto_pwhash_OPSLIMIT_INTERACTIVE as u64;
 const MEMLIMIT_INTERACTIVE: usize = ffi::crypto_pwhash_MEMLIMIT_INTERACTIVE as usize;
+const GENERICHASH_BYTES: usize = ffi::crypto_generichash_BYTES as usize;
+const RANDOM_TOKEN_BYTES: usize = 32;
+pub const SECRETBOX_KEYBYTES: usize = ffi::crypto_secretbox_KEYBYTES as usize;
+const SECRETBOX_NONCEBYTES: usize = ffi::crypto_secretbox_NONCEBYTES as usize;
+const SECRETBOX_MACBYTES: usize = ffi::crypto_secretbox_MACBYTES as usize;
 
 /*
 pub fn crypto_pwhash_str(
@@ -95,6 +100,160 @@ pub fn verify_password(hash: &str, password: &str) -> Result<(), ()> {
     pwhash_verify(&hash_bytes, password.as_bytes())
 }
 
+/*
+pub fn randombytes_buf(buf: *mut libc::c_void, size: usize);
+*/
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes: [u8; N] = [0; N];
+    unsafe { ffi::randombytes_buf(bytes.as_mut_ptr() as *mut _, N) };
+
+    bytes
+}
+
+/*
+pub fn crypto_generichash(
+    out: *mut libc::c_uchar,
+    outlen: usize,
+    in_: *const libc::c_uchar,
+    inlen: libc::c_ulonglong,
+    key: *const libc::c_uchar,
+    keylen: usize,
+) -> libc::c_int;
+*/
+fn generichash(input: &[u8]) -> Result<[u8; GENERICHASH_BYTES], ()> {
+    let mut hash: [u8; GENERICHASH_BYTES] = [0; GENERICHASH_BYTES];
+
+    if unsafe {
+        ffi::crypto_generichash(
+            hash.as_mut_ptr(),
+            GENERICHASH_BYTES,
+            input.as_ptr(),
+            input.len() as u64,
+            std::ptr::null(),
+            0,
+        )
+    } == 0
+    {
+        Ok(hash)
+    } else {
+        Err(())
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, ()> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return Err(());
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| ()))
+        .collect()
+}
+
+/*
+pub fn crypto_secretbox_easy(
+    c: *mut libc::c_uchar,
+    m: *const libc::c_uchar,
+    mlen: libc::c_ulonglong,
+    n: *const libc::c_uchar,
+    k: *const libc::c_uchar,
+) -> libc::c_int;
+*/
+fn secretbox(key: &[u8; SECRETBOX_KEYBYTES], message: &[u8]) -> Result<Vec<u8>, ()> {
+    let nonce = random_bytes::<SECRETBOX_NONCEBYTES>();
+    let mut ciphertext: Vec<u8> = vec![0; SECRETBOX_MACBYTES + message.len()];
+
+    if unsafe {
+        ffi::crypto_secretbox_easy(
+            ciphertext.as_mut_ptr(),
+            message.as_ptr(),
+            message.len() as u64,
+            nonce.as_ptr(),
+            key.as_ptr(),
+        )
+    } == 0
+    {
+        // nonce is not secret, it's stored in front so that decryption can find it
+        Ok([nonce.to_vec(), ciphertext].concat())
+    } else {
+        Err(())
+    }
+}
+
+/*
+pub fn crypto_secretbox_open_easy(
+    m: *mut libc::c_uchar,
+    c: *const libc::c_uchar,
+    clen: libc::c_ulonglong,
+    n: *const libc::c_uchar,
+    k: *const libc::c_uchar,
+) -> libc::c_int;
+*/
+fn secretbox_open(key: &[u8; SECRETBOX_KEYBYTES], sealed: &[u8]) -> Result<Vec<u8>, ()> {
+    if sealed.len() < SECRETBOX_NONCEBYTES + SECRETBOX_MACBYTES {
+        return Err(());
+    }
+
+    let (nonce, ciphertext) = sealed.split_at(SECRETBOX_NONCEBYTES);
+    let mut message: Vec<u8> = vec![0; ciphertext.len() - SECRETBOX_MACBYTES];
+
+    if unsafe {
+        ffi::crypto_secretbox_open_easy(
+            message.as_mut_ptr(),
+            ciphertext.as_ptr(),
+            ciphertext.len() as u64,
+            nonce.as_ptr(),
+            key.as_ptr(),
+        )
+    } == 0
+    {
+        Ok(message)
+    } else {
+        Err(())
+    }
+}
+
+/* Returns random string suitable to be used as a secret, e.g. an API token */
+pub fn generate_token() -> String {
+    to_hex(&random_bytes::<RANDOM_TOKEN_BYTES>())
+}
+
+/* Unlike passwords, tokens are long and random, so fast unsalted hash is enough.
+Hash being deterministic allows looking the token up by its hash directly. Only the
+hash is ever stored, so tokens can't be taken from the database and used. */
+pub fn hash_token(token: &str) -> anyhow::Result<String> {
+    match generichash(token.as_bytes()) {
+        Ok(hash) => Ok(to_hex(&hash)),
+        Err(_) => Err(anyhow::anyhow!("Hashing token failed")),
+    }
+}
+
+/* Key is expected as hex string, e.g. from `openssl rand -hex 32` */
+pub fn parse_key(hex: &str) -> Result<[u8; SECRETBOX_KEYBYTES], ()> {
+    let bytes = from_hex(hex.trim())?;
+
+    bytes.try_into().map_err(|_| ())
+}
+
+/* For secrets that have to be read back later, unlike passwords and tokens that
+only need to be compared. Result is hex encoded, to be stored as text */
+pub fn encrypt(key: &[u8; SECRETBOX_KEYBYTES], secret: &[u8]) -> Result<String, ()> {
+    let sealed = secretbox(key, secret)?;
+
+    Ok(to_hex(&sealed))
+}
+
+pub fn decrypt(key: &[u8; SECRETBOX_KEYBYTES], encrypted: &str) -> Result<Vec<u8>, ()> {
+    let sealed = from_hex(encrypted)?;
+
+    secretbox_open(key, &sealed)
+}
+
 pub fn init_crypto() -> Result<(), ()> {
     if unsafe { ffi::sodium_init() } >= 0 {
         Ok(())
@@ -126,6 +285,51 @@ mod tests {
         assert!(verify_password(&hash, "wrong password").is_err());
     }
 
+    #[test]
+    fn test_generate_token() {
+        let token = generate_token();
+        assert_eq!(token.len(), 2 * RANDOM_TOKEN_BYTES);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, generate_token());
+    }
+
+    #[test]
+    fn test_hash_token() {
+        let hash = hash_token("some token").unwrap();
+        assert_eq!(hash.len(), 2 * GENERICHASH_BYTES);
+        assert_eq!(hash, hash_token("some token").unwrap());
+        assert_ne!(hash, hash_token("other token").unwrap());
+    }
+
+    #[test]
+    fn test_to_hex() {
+        assert_eq!(to_hex(&[0, 15, 16, 255]), "000f10ff");
+    }
+
+    #[test]
+    fn test_from_hex() {
+        assert_eq!(from_hex("000f10ff"), Ok(vec![0, 15, 16, 255]));
+        assert!(from_hex("abc").is_err());
+        assert!(from_hex("zz").is_err());
+    }
+
+    #[test]
+    fn test_parse_key() {
+        let key = to_hex(&[7; SECRETBOX_KEYBYTES]);
+        assert_eq!(parse_key(&key), Ok([7; SECRETBOX_KEYBYTES]));
+        assert!(parse_key("00ff").is_err());
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        init_crypto().unwrap();
+        let key = [1; SECRETBOX_KEYBYTES];
+        let encrypted = encrypt(&key, b"some secret").unwrap();
+        assert_ne!(encrypted, encrypt(&key, b"some secret").unwrap());
+        assert_eq!(decrypt(&key, &encrypted), Ok(b"some secret".to_vec()));
+        assert!(decrypt(&[2; SECRETBOX_KEYBYTES], &encrypted).is_err());
+    }
+
     #[test]
     #[allow(non_snake_case)]
     fn test_string_to_u8_array_pads_with_zero_bytes_to_STRBYTES_len() {
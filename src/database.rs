@@ -1,36 +1,120 @@
 use sqlx::pool::PoolConnection;
-use sqlx::sqlite::SqlitePool;
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteSynchronous,
+};
 use sqlx::Sqlite;
 use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
 const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
+const POOL_SIZE_ENV_VAR: &str = "DATABASE_POOL_SIZE";
+const DEFAULT_POOL_SIZE: u32 = 8;
+// how long writer waits for another one to finish, before giving up with "database is locked"
+const BUSY_TIMEOUT_S: u64 = 10;
 
-fn get_fallback_database_url() -> String {
-    let current_dir = env::current_dir().expect("Cannot read current exectuable path, aborting!");
+/* Stored in user_version pragma, every delta that changes the schema has to set it
+to its own number, and bump this one */
+pub const SCHEMA_VERSION: i32 = 22;
 
-    format!("sqlite:{}/db/bst.db", current_dir.display())
+fn get_fallback_database_url() -> anyhow::Result<String> {
+    let current_dir = match env::current_dir() {
+        Ok(value) => value,
+        Err(error) => {
+            return Err(anyhow::anyhow!(
+                "Cannot read current directory for default database location: {}",
+                error
+            ))
+        }
+    };
+
+    Ok(format!("sqlite:{}/db/bst.db", current_dir.display()))
 }
 
-async fn get_db_pool() -> SqlitePool {
-    let database_url = match env::var(DATABASE_URL_ENV_VAR) {
-        Ok(value) => value,
+fn get_database_url() -> anyhow::Result<String> {
+    match env::var(DATABASE_URL_ENV_VAR) {
+        Ok(value) => Ok(value),
         Err(_) => {
-            let fallback_database_url = get_fallback_database_url();
+            let fallback_database_url = get_fallback_database_url()?;
             println!(
                 "{} env variable unset, falling back to {}.",
                 DATABASE_URL_ENV_VAR, fallback_database_url,
             );
 
-            fallback_database_url
+            Ok(fallback_database_url)
         }
+    }
+}
+
+fn parse_pool_size(value: Option<&str>) -> anyhow::Result<u32> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(DEFAULT_POOL_SIZE),
     };
 
-    match SqlitePool::connect(&database_url).await {
-        Ok(pool) => pool,
-        Err(_) => panic!(
-            "Could not create db connection to {}, aborting!",
-            database_url
-        ),
+    match value.trim().parse::<u32>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(anyhow::anyhow!(
+            "{} must be a positive number, got '{}'",
+            POOL_SIZE_ENV_VAR,
+            value
+        )),
+    }
+}
+
+/* Path of the database file, for things that work with the file rather than
+through connection, like restoring backups */
+pub fn get_database_path() -> anyhow::Result<PathBuf> {
+    Ok(get_path_from_url(&get_database_url()?))
+}
+
+fn get_path_from_url(database_url: &str) -> PathBuf {
+    let path = database_url
+        .trim_start_matches("sqlite:")
+        .trim_start_matches("//");
+    // drop connection options, e.g. ?mode=rwc
+    let path = match path.split_once('?') {
+        Some((path, _)) => path,
+        None => path,
+    };
+
+    PathBuf::from(path)
+}
+
+/* WAL lets the UI read while the job server writes. Synchronous NORMAL is safe with
+WAL, it can only lose the last transactions on power loss, never corrupt the file */
+async fn get_db_pool() -> anyhow::Result<SqlitePool> {
+    let database_url = get_database_url()?;
+    let pool_size = parse_pool_size(env::var(POOL_SIZE_ENV_VAR).ok().as_deref())?;
+
+    let options = match SqliteConnectOptions::from_str(&database_url) {
+        Ok(value) => value,
+        Err(error) => {
+            return Err(anyhow::anyhow!(
+                "Invalid database url {}: {}",
+                database_url,
+                error
+            ))
+        }
+    };
+    let options = options
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(Duration::from_secs(BUSY_TIMEOUT_S))
+        .foreign_keys(true);
+
+    match SqlitePoolOptions::new()
+        .max_connections(pool_size)
+        .connect_with(options)
+        .await
+    {
+        Ok(pool) => Ok(pool),
+        Err(error) => Err(anyhow::anyhow!(
+            "Could not open database {}: {}",
+            database_url,
+            error
+        )),
     }
 }
 
@@ -41,10 +125,10 @@ pub struct Database {
 }
 
 impl Database {
-    pub async fn init() -> Database {
-        Database {
-            pool: get_db_pool().await,
-        }
+    pub async fn init() -> anyhow::Result<Database> {
+        Ok(Database {
+            pool: get_db_pool().await?,
+        })
     }
 
     pub async fn acquire_db_conn(&self) -> Result<DatabaseConnection, anyhow::Error> {
@@ -52,4 +136,65 @@ impl Database {
 
         Ok(conn)
     }
+
+    /* In-memory database with all the deltas applied, for tests that need the real
+    schema. Single connection that is never closed, as the database lives in it */
+    #[cfg(test)]
+    pub async fn init_for_tests() -> anyhow::Result<Database> {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")?.foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
+
+        let db_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("db");
+        let mut scripts = vec![db_dir.join("init.sql")];
+        for version in 1..=SCHEMA_VERSION {
+            scripts.push(db_dir.join(format!("delta.{}.sql", version)));
+        }
+
+        let mut conn = pool.acquire().await?;
+        for script in scripts {
+            let sql = std::fs::read_to_string(&script)?;
+            sqlx::raw_sql(&sql).execute(&mut *conn).await?;
+        }
+        drop(conn);
+
+        Ok(Database { pool: pool })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pool_size() {
+        assert_eq!(parse_pool_size(None).unwrap(), DEFAULT_POOL_SIZE);
+        assert_eq!(parse_pool_size(Some("4")).unwrap(), 4);
+        assert_eq!(parse_pool_size(Some(" 4\n")).unwrap(), 4);
+
+        assert!(parse_pool_size(Some("0")).is_err());
+        assert!(parse_pool_size(Some("-1")).is_err());
+        assert!(parse_pool_size(Some("many")).is_err());
+    }
+
+    #[test]
+    fn test_get_path_from_url() {
+        assert_eq!(
+            get_path_from_url("sqlite:/srv/bst/db/bst.db"),
+            PathBuf::from("/srv/bst/db/bst.db")
+        );
+        assert_eq!(
+            get_path_from_url("sqlite:///srv/bst/db/bst.db?mode=rwc"),
+            PathBuf::from("/srv/bst/db/bst.db")
+        );
+        assert_eq!(
+            get_path_from_url("sqlite://db/bst.db"),
+            PathBuf::from("db/bst.db")
+        );
+    }
 }
@@ -1,34 +1,148 @@
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::http::uri::Origin;
+use rocket::http::Method;
 use rocket::request::Outcome;
-use rocket::{Data, Request};
+use rocket::{Data, Request, Response};
+use std::sync::Arc;
 
+use crate::audit_log::{get_route_target, AuditLogEntry, NewAuditLogEntry};
+use crate::controllers::login::ClientIp;
+use crate::database::Database;
 use crate::user::User;
 
-pub struct GateKeeper {}
+// routes that do their own authorization, e.g. with one-time token
+const PUBLIC_API_PATHS: [&str; 4] = [
+    "/api/login",
+    "/api/login/totp",
+    "/api/account/reset",
+    "/api/register",
+];
+
+// user's own login security, read-only users need it too, e.g. to change leaked password
+const SELF_ACCOUNT_PATH_PREFIXES: [&str; 3] = [
+    "/api/account/password",
+    "/api/account/totp",
+    "/api/sessions",
+];
+
+// read-only routes that visitors can browse without login, unless server is private
+const PUBLIC_READ_PATH_PREFIX: &str = "/api/public/";
+
+/* Path as it was requested, before it's possibly rewritten to /404 */
+struct RequestedPath(Option<String>);
+
+fn is_self_account_path(path: &str) -> bool {
+    SELF_ACCOUNT_PATH_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+}
+
+pub struct GateKeeper {
+    pub public_browsing: bool,
+}
 
 #[rocket::async_trait]
 impl Fairing for GateKeeper {
     fn info(&self) -> Info {
         Info {
             name: "Authorize and log writes",
-            kind: Kind::Request,
+            kind: Kind::Request | Kind::Response,
         }
     }
 
     async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
-        let path = request.uri().path().as_str();
+        let path = request.uri().path().to_string();
+        request.local_cache(|| RequestedPath(Some(path.clone())));
+
+        if !path.starts_with("/api/") || PUBLIC_API_PATHS.contains(&path.as_str()) {
+            return;
+        }
 
-        if !path.starts_with("/api/") || path == "/api/login" {
+        if self.public_browsing
+            && path.starts_with(PUBLIC_READ_PATH_PREFIX)
+            && request.method() == Method::Get
+        {
             return;
         }
 
-        match request.guard::<&User>().await {
-            Outcome::Success(_) => return,
-            _ => {
-                let not_found = Origin::parse("/404").unwrap();
-                request.set_uri(not_found);
+        // read-only users can browse, but every other method is treated as write. Read-only
+        // API token doesn't get the exception for own account, it's not meant for that
+        let is_allowed = match request.guard::<&User>().await {
+            Outcome::Success(user) => {
+                user.can_write()
+                    || request.method() == Method::Get
+                    || (is_self_account_path(&path) && !request.headers().contains("Authorization"))
             }
+            _ => false,
         };
+
+        if !is_allowed {
+            let not_found = Origin::parse("/404").unwrap();
+            request.set_uri(not_found);
+        }
+    }
+
+    /* Records every write attempt, including the rejected ones, so that it's
+    possible to tell who changed or removed what */
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let path = match &request.local_cache(|| RequestedPath(None)).0 {
+            Some(value) => value,
+            None => return,
+        };
+
+        if !path.starts_with("/api/") || request.method() == Method::Get {
+            return;
+        }
+
+        let db = match request.rocket().state::<Arc<Database>>() {
+            Some(value) => value,
+            None => return,
+        };
+
+        let username = match request.guard::<&User>().await {
+            Outcome::Success(user) => Some(user.username.as_str()),
+            _ => None,
+        };
+
+        // same address as for login lockout, so that it can't be forged with X-Real-IP
+        let ip = match request.guard::<ClientIp>().await {
+            Outcome::Success(client_ip) => client_ip.0,
+            _ => None,
+        };
+
+        // rewritten requests end up in catch-all route, which has nothing to pick from
+        let target = match request.route() {
+            Some(route) if request.uri().path().as_str() == path.as_str() => {
+                get_route_target(route.uri.as_str(), path)
+            }
+            _ => None,
+        };
+
+        let entry = NewAuditLogEntry {
+            username: username,
+            ip: ip.map(|ip| ip.to_string()),
+            method: request.method().as_str(),
+            path: path,
+            target: target,
+            status: response.status().code,
+        };
+        if let Err(error) = AuditLogEntry::add(db, entry).await {
+            log::error!("Could not write audit log for {}: {:?}", path, error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_self_account_path() {
+        assert!(is_self_account_path("/api/account/password"));
+        assert!(is_self_account_path("/api/account/totp/confirm"));
+        assert!(is_self_account_path("/api/sessions/3"));
+
+        assert!(!is_self_account_path("/api/account/import"));
+        assert!(!is_self_account_path("/api/series/B0SERIES01"));
     }
 }
@@ -3,9 +3,26 @@ use std::fs;
 use std::io;
 use ts_rs::{ExportError, TS};
 
-use crate::books::GetAllBooksResult;
+use crate::audit_log::GetAuditLogResult;
+use crate::books::{GetAllBooksResult, GetUpcomingBooksResult};
+use crate::controllers::accounts::GetAllUsersResult;
+use crate::controllers::login::LoginResult;
+use crate::goals::GetAllGoalsResult;
+use crate::import::ImportReport;
+use crate::invites::{CreateInviteResult, GetAllInvitesResult};
+use crate::password_resets::CreatePasswordResetResult;
+use crate::queue::GetQueueResult;
+use crate::scraper::events::JobEvent;
 use crate::scraper::job::GetAllJobsResult;
-use crate::series::{AddSeriesResult, GetAllSeriesResult};
+use crate::scraper::server::JobServerHealth;
+use crate::series::{
+    AddSeriesResult, GetAllPublicSeriesResult, GetAllSeriesResult, GetSeriesDetailResult,
+};
+use crate::sessions::GetAllSessionsResult;
+use crate::stats::GetStatsResult;
+use crate::tokens::{CreateTokenResult, GetAllTokensResult};
+use crate::totp::{RecoveryCodesResult, TotpEnrollment, TotpStatus};
+use crate::user_data::{UserExport, UserImportReport};
 
 fn export_all() -> Result<(), ExportError> {
     // exports type with all dependencies, see https://docs.rs/ts-rs/latest/src/ts_rs/lib.rs.html
@@ -13,8 +30,33 @@ fn export_all() -> Result<(), ExportError> {
     AddSeriesResult::export_all()?;
     GetAllBooksResult::export_all()?;
     GetAllSeriesResult::export_all()?;
+    GetAllPublicSeriesResult::export_all()?;
+    GetSeriesDetailResult::export_all()?;
+    GetUpcomingBooksResult::export_all()?;
+    GetStatsResult::export_all()?;
+    GetAllGoalsResult::export_all()?;
+    GetQueueResult::export_all()?;
+    ImportReport::export_all()?;
+    UserExport::export_all()?;
+    UserImportReport::export_all()?;
 
     GetAllJobsResult::export_all()?;
+    JobEvent::export_all()?;
+    JobServerHealth::export_all()?;
+
+    LoginResult::export_all()?;
+    TotpStatus::export_all()?;
+    TotpEnrollment::export_all()?;
+    RecoveryCodesResult::export_all()?;
+
+    CreateTokenResult::export_all()?;
+    GetAllTokensResult::export_all()?;
+    GetAllSessionsResult::export_all()?;
+    GetAllUsersResult::export_all()?;
+    CreatePasswordResetResult::export_all()?;
+    CreateInviteResult::export_all()?;
+    GetAllInvitesResult::export_all()?;
+    GetAuditLogResult::export_all()?;
 
     Ok(())
 }
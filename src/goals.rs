@@ -0,0 +1,271 @@
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+use sqlx::sqlite::SqliteConnection;
+use ts_rs::TS;
+
+use crate::books::Book;
+use crate::common::{now, today, TS_FILE};
+use crate::database::Database;
+use crate::user::User;
+
+const MAX_TARGET: u32 = 1000;
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct ReadingGoal {
+    pub year: i32,
+    pub target: u32,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GoalProgress {
+    pub year: i32,
+    pub target: u32,
+    pub read: u32,
+    pub remaining: u32,
+    // how many should have been read by today to keep up, same as target for past years
+    pub expected_by_now: u32,
+    // at the current pace, None if nothing has been read yet or the year is over
+    pub projected_finish_date: Option<String>,
+    // unread books in subscribed series released by the end of the year, enough of
+    // them to reach the target. Fewer than remaining if there aren't enough
+    pub suggested_books: Vec<Book>,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetAllGoalsResult {
+    pub goals: Vec<GoalProgress>,
+}
+
+/* Counts finished readings, re-reads included, same as stats do */
+pub async fn fetch_by_user(db: &Database, user: &User) -> anyhow::Result<GetAllGoalsResult> {
+    let goals = fetch_goals(db, user).await?;
+
+    let today = NaiveDate::parse_from_str(&today(), "%Y-%m-%d")?;
+    let mut result = Vec::new();
+    for goal in goals {
+        let read = fetch_read_count(db, user, goal.year).await?;
+        let remaining = goal.target.saturating_sub(read);
+        let suggested_books = match remaining > 0 && goal.year >= today.year() {
+            true => fetch_unread_books(db, user, goal.year, remaining).await?,
+            false => Vec::new(),
+        };
+
+        result.push(GoalProgress {
+            year: goal.year,
+            target: goal.target,
+            read: read,
+            remaining: remaining,
+            expected_by_now: get_expected_by_now(goal.year, goal.target, today),
+            projected_finish_date: get_projected_finish_date(goal.year, goal.target, read, today)
+                .map(|date| date.format("%Y-%m-%d").to_string()),
+            suggested_books: suggested_books,
+        });
+    }
+
+    Ok(GetAllGoalsResult { goals: result })
+}
+
+pub async fn fetch_goals(db: &Database, user: &User) -> anyhow::Result<Vec<ReadingGoal>> {
+    let mut conn = db.acquire_db_conn().await?;
+    let goals = sqlx::query_as::<_, ReadingGoal>(
+        "SELECT year, target FROM reading_goals WHERE username = ?1 ORDER BY year DESC",
+    )
+    .bind(&user.username)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(goals)
+}
+
+pub async fn set(db: &Database, user: &User, year: i32, target: u32) -> anyhow::Result<()> {
+    let mut conn = db.acquire_db_conn().await?;
+    set_with_conn(&mut conn, user, year, target).await
+}
+
+pub async fn set_with_conn(
+    conn: &mut SqliteConnection,
+    user: &User,
+    year: i32,
+    target: u32,
+) -> anyhow::Result<()> {
+    let time_updated = now();
+    sqlx::query!(
+        "INSERT INTO reading_goals (username, year, target, time_updated)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT (username, year) DO UPDATE
+        SET target = ?3, time_updated = ?4",
+        user.username,
+        year,
+        target,
+        time_updated,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete(db: &Database, user: &User, year: i32) -> anyhow::Result<bool> {
+    let mut conn = db.acquire_db_conn().await?;
+    let result = sqlx::query!(
+        "DELETE FROM reading_goals WHERE username = ?1 AND year = ?2",
+        user.username,
+        year,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+async fn fetch_read_count(db: &Database, user: &User, year: i32) -> anyhow::Result<u32> {
+    let mut conn = db.acquire_db_conn().await?;
+    let read = sqlx::query_scalar::<_, u32>(
+        "SELECT COUNT(1) FROM readings
+        WHERE username = ?1 AND status = 'read' AND substr(finish_date, 1, 4) = ?2",
+    )
+    .bind(&user.username)
+    .bind(format!("{:04}", year))
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(read)
+}
+
+/* Next books to read in each series come first, so suggestions are spread across
+series instead of listing the whole backlog of the first one */
+async fn fetch_unread_books(
+    db: &Database,
+    user: &User,
+    year: i32,
+    limit: u32,
+) -> anyhow::Result<Vec<Book>> {
+    let mut conn = db.acquire_db_conn().await?;
+    let books = sqlx::query_as::<_, Book>(
+        "SELECT books.*
+        FROM books
+        JOIN subscriptions USING (series_asin)
+        WHERE subscriptions.username = ?1
+          AND (books.release_date IS NULL OR books.release_date <= ?2)
+          AND books.asin NOT IN (
+            SELECT book_asin FROM readings WHERE username = ?1 AND status = 'read'
+          )
+        ORDER BY
+          ROW_NUMBER() OVER (PARTITION BY books.series_asin ORDER BY books.ordinal),
+          books.release_date,
+          books.title
+        LIMIT ?3",
+    )
+    .bind(&user.username)
+    .bind(format!("{:04}-12-31", year))
+    .bind(limit)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(books)
+}
+
+pub fn check_target(target: u32) -> Result<(), String> {
+    if target == 0 || target > MAX_TARGET {
+        return Err(format!(
+            "Goal has to be between 1 and {} books!",
+            MAX_TARGET
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn check_year(year: i32) -> Result<(), String> {
+    if !(1000..=9999).contains(&year) {
+        return Err(format!("'{}' is not a valid year!", year));
+    }
+
+    Ok(())
+}
+
+/* Returns (days passed including today, days in the year). Past years are over, and
+future ones haven't started yet */
+fn get_year_elapsed(year: i32, today: NaiveDate) -> (u32, u32) {
+    let days_in_year = match NaiveDate::from_ymd_opt(year, 2, 29) {
+        Some(_) => 366,
+        None => 365,
+    };
+
+    let elapsed = match year.cmp(&today.year()) {
+        std::cmp::Ordering::Less => days_in_year,
+        std::cmp::Ordering::Equal => today.ordinal(),
+        std::cmp::Ordering::Greater => 0,
+    };
+
+    (elapsed, days_in_year)
+}
+
+fn get_expected_by_now(year: i32, target: u32, today: NaiveDate) -> u32 {
+    let (elapsed, days_in_year) = get_year_elapsed(year, today);
+    target * elapsed / days_in_year
+}
+
+fn get_projected_finish_date(
+    year: i32,
+    target: u32,
+    read: u32,
+    today: NaiveDate,
+) -> Option<NaiveDate> {
+    let (elapsed, days_in_year) = get_year_elapsed(year, today);
+    if read == 0 || elapsed == 0 || elapsed == days_in_year {
+        return None;
+    }
+
+    // read/elapsed books per day, so target is reached on day target * elapsed / read
+    let day = (target * elapsed).div_ceil(read);
+    NaiveDate::from_yo_opt(year, 1)?.checked_add_days(chrono::Days::new(day as u64 - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_check_target() {
+        assert!(check_target(1).is_ok());
+        assert!(check_target(MAX_TARGET).is_ok());
+
+        assert!(check_target(0).is_err());
+        assert!(check_target(MAX_TARGET + 1).is_err());
+    }
+
+    #[test]
+    fn test_get_expected_by_now() {
+        let today = date("2025-07-02"); // day 183 of 365
+
+        assert_eq!(get_expected_by_now(2025, 50, today), 25);
+        assert_eq!(get_expected_by_now(2024, 50, today), 50);
+        assert_eq!(get_expected_by_now(2026, 50, today), 0);
+    }
+
+    #[test]
+    fn test_get_projected_finish_date() {
+        let today = date("2025-01-10");
+
+        assert_eq!(
+            get_projected_finish_date(2025, 10, 5, today),
+            Some(date("2025-01-20"))
+        );
+        // way behind the pace, goal would be reached only in 2027
+        assert_eq!(
+            get_projected_finish_date(2025, 400, 5, today),
+            Some(date("2027-03-11"))
+        );
+
+        assert_eq!(get_projected_finish_date(2025, 10, 0, today), None);
+        assert_eq!(get_projected_finish_date(2024, 10, 5, today), None);
+        assert_eq!(get_projected_finish_date(2026, 10, 5, today), None);
+    }
+}
@@ -0,0 +1,486 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use ts_rs::TS;
+
+use crate::books::Book;
+use crate::common::TS_FILE;
+use crate::credentials::Credentials;
+use crate::database::Database;
+use crate::reads::{is_valid_date, Reading, ReadingParams, ReadingStatus};
+use crate::user::User;
+
+// how similar normalized titles must be, when they aren't exactly the same
+const MIN_TITLE_SIMILARITY: f64 = 0.85;
+
+#[derive(Debug, PartialEq)]
+enum ImportFormat {
+    Goodreads,
+    StoryGraph,
+}
+
+/* One row of the export, only books that were finished are of any interest */
+#[derive(Debug, PartialEq)]
+pub struct ImportRow {
+    line: u32,
+    title: String,
+    author: String,
+    // ISBNs, or ASIN, whatever the export has
+    identifiers: Vec<String>,
+    // (start, finish), finish is missing when the book is marked read without date
+    reads: Vec<(Option<String>, Option<String>)>,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct UnmatchedRow {
+    pub line: u32,
+    pub title: String,
+    pub author: String,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct ImportReport {
+    pub imported: u32,
+    // already imported before, importing same file twice changes nothing
+    pub skipped: u32,
+    pub unmatched: Vec<UnmatchedRow>,
+}
+
+/* Rows are matched to known books only, books of series nobody tracks are reported
+as unmatched. Finished readings are added with the original dates */
+pub async fn import_rows(
+    db: &Database,
+    user: &User,
+    rows: Vec<ImportRow>,
+) -> anyhow::Result<ImportReport> {
+    let books = Book::fetch_all(db).await?;
+
+    let mut existing: HashSet<(String, Option<String>)> = Reading::fetch_by_user(db, user)
+        .await?
+        .into_iter()
+        .filter(|reading| reading.status == ReadingStatus::Read)
+        .map(|reading| (reading.book_asin, reading.finish_date))
+        .collect();
+
+    let mut report = ImportReport {
+        imported: 0,
+        skipped: 0,
+        unmatched: Vec::new(),
+    };
+
+    for row in rows {
+        let book = match find_book(&books, &row) {
+            Some(value) => value,
+            None => {
+                report.unmatched.push(UnmatchedRow {
+                    line: row.line,
+                    title: row.title,
+                    author: row.author,
+                });
+                continue;
+            }
+        };
+
+        for (start_date, finish_date) in row.reads {
+            if !existing.insert((book.asin.clone(), finish_date.clone())) {
+                report.skipped += 1;
+                continue;
+            }
+
+            // not normalized, that would date reads without finish date to today
+            let params = ReadingParams {
+                status: ReadingStatus::Read,
+                start_date: start_date,
+                finish_date: finish_date,
+                progress_percent: Some(100),
+                progress_location: None,
+            };
+            Reading::create(db, user, &book.asin, &params).await?;
+            report.imported += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+/* Command line version of the upload, prints the report */
+pub async fn import_file(db: Database, username: String, path: PathBuf) {
+    let user = match Credentials::fetch_by_username(&db, &username).await {
+        Ok(Some(creds)) => User {
+            username: creds.username,
+            role: creds.role,
+        },
+        Ok(None) => {
+            println!("User '{}' does not exist", username);
+            return;
+        }
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    let rows = match fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| parse_csv(&content))
+    {
+        Ok(value) => value,
+        Err(e) => {
+            println!("Can't read '{}': {}", path.display(), e);
+            return;
+        }
+    };
+
+    let report = match import_rows(&db, &user, rows).await {
+        Ok(value) => value,
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    println!(
+        "Imported {} reads, skipped {} already imported.",
+        report.imported, report.skipped
+    );
+    if !report.unmatched.is_empty() {
+        println!(
+            "No matching book found for {} rows:",
+            report.unmatched.len()
+        );
+        for row in report.unmatched {
+            println!("  line {}: {} by {}", row.line, row.title, row.author);
+        }
+    }
+}
+
+fn detect_format(headers: &csv::StringRecord) -> Option<ImportFormat> {
+    let has = |name: &str| headers.iter().any(|header| header == name);
+
+    if has("Exclusive Shelf") && has("Date Read") {
+        return Some(ImportFormat::Goodreads);
+    }
+    if has("Read Status") && has("Last Date Read") {
+        return Some(ImportFormat::StoryGraph);
+    }
+    None
+}
+
+pub fn parse_csv(content: &str) -> anyhow::Result<Vec<ImportRow>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let headers = reader.headers()?.clone();
+    let format = match detect_format(&headers) {
+        Some(value) => value,
+        None => {
+            return Err(anyhow::anyhow!(
+                "Unknown CSV format, expected Goodreads or StoryGraph export"
+            ))
+        }
+    };
+    let columns: HashMap<&str, usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(index, header)| (header, index))
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |name: &str| -> String {
+            columns
+                .get(name)
+                .and_then(|index| record.get(*index))
+                .map(clean_field)
+                .unwrap_or_default()
+        };
+
+        let (is_read, identifiers, reads) = match format {
+            ImportFormat::Goodreads => (
+                field("Exclusive Shelf") == "read",
+                vec![field("ISBN"), field("ISBN13")],
+                vec![(None, parse_date(&field("Date Read")))],
+            ),
+            ImportFormat::StoryGraph => (
+                field("Read Status") == "read",
+                vec![field("ISBN/UID")],
+                parse_date_ranges(&field("Dates Read"), &field("Last Date Read")),
+            ),
+        };
+
+        if !is_read {
+            continue;
+        }
+
+        rows.push(ImportRow {
+            line: record
+                .position()
+                .map(|position| position.line() as u32)
+                .unwrap_or(0),
+            title: field("Title"),
+            author: match format {
+                ImportFormat::Goodreads => field("Author"),
+                ImportFormat::StoryGraph => field("Authors"),
+            },
+            identifiers: identifiers
+                .into_iter()
+                .filter(|identifier| !identifier.is_empty())
+                .collect(),
+            reads: reads,
+        });
+    }
+
+    Ok(rows)
+}
+
+/* Goodreads wraps ISBNs as ="0123456789", so that spreadsheets keep leading zeros */
+fn clean_field(value: &str) -> String {
+    value
+        .trim()
+        .trim_start_matches('=')
+        .trim_matches('"')
+        .trim()
+        .to_string()
+}
+
+/* Both exports use YYYY/MM/DD */
+fn parse_date(value: &str) -> Option<String> {
+    let date = value.trim().replace('/', "-");
+    match is_valid_date(&date) {
+        true => Some(date),
+        false => None,
+    }
+}
+
+/* StoryGraph lists every read as "start-finish" or just "finish", comma separated */
+fn parse_date_ranges(
+    dates_read: &str,
+    last_date_read: &str,
+) -> Vec<(Option<String>, Option<String>)> {
+    let reads: Vec<(Option<String>, Option<String>)> = dates_read
+        .split(',')
+        .filter(|range| !range.trim().is_empty())
+        .map(|range| match range.split_once('-') {
+            Some((start, finish)) => (parse_date(start), parse_date(finish)),
+            None => (None, parse_date(range)),
+        })
+        .collect();
+
+    match reads.is_empty() {
+        true => vec![(None, parse_date(last_date_read))],
+        false => reads,
+    }
+}
+
+fn find_book<'a>(books: &'a [Book], row: &ImportRow) -> Option<&'a Book> {
+    // ASIN of print book is its ISBN-10, and StoryGraph keeps ASIN of Kindle editions
+    let by_identifier = books.iter().find(|book| {
+        row.identifiers
+            .iter()
+            .any(|identifier| identifier.eq_ignore_ascii_case(&book.asin))
+    });
+    if by_identifier.is_some() {
+        return by_identifier;
+    }
+
+    let title = normalize_title(&row.title);
+    let surname = match get_surname(&row.author) {
+        Some(value) => value,
+        None => return None,
+    };
+
+    books
+        .iter()
+        .filter(|book| normalize(&book.author).contains(&surname))
+        .map(|book| (book, get_similarity(&title, &normalize_title(&book.title))))
+        .filter(|(_, similarity)| *similarity >= MIN_TITLE_SIMILARITY)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(book, _)| book)
+}
+
+/* Lowercase alphanumeric words separated by single space */
+fn normalize(value: &str) -> String {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/* Series info is in parentheses on Goodreads, e.g. "Title (Series, #1)", and
+subtitles differ between editions, so both are dropped */
+fn normalize_title(title: &str) -> String {
+    let title = match title.find(['(', '[', ':']) {
+        Some(index) => &title[..index],
+        None => title,
+    };
+    normalize(title)
+}
+
+/* Initials are formatted differently everywhere, surname is the reliable part */
+fn get_surname(author: &str) -> Option<String> {
+    normalize(author)
+        .split(' ')
+        .last()
+        .filter(|surname| !surname.is_empty())
+        .map(String::from)
+}
+
+/* Dice coefficient over character bigrams, 1.0 for the same strings */
+fn get_similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+
+    let bigrams = |value: &str| -> Vec<(char, char)> {
+        let chars: Vec<char> = value.chars().collect();
+        chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+    };
+    let a = bigrams(a);
+    let mut b = bigrams(b);
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let total = a.len() + b.len();
+    let mut common = 0;
+    for bigram in a {
+        if let Some(index) = b.iter().position(|other| *other == bigram) {
+            b.swap_remove(index);
+            common += 1;
+        }
+    }
+
+    2.0 * common as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(asin: &str, title: &str, author: &str) -> Book {
+        Book {
+            asin: asin.to_string(),
+            series_asin: String::from("S"),
+            ordinal: 1,
+            title: title.to_string(),
+            author: author.to_string(),
+            release_date: None,
+            time_first_seen: 0,
+        }
+    }
+
+    fn row(title: &str, author: &str, identifiers: Vec<&str>) -> ImportRow {
+        ImportRow {
+            line: 2,
+            title: title.to_string(),
+            author: author.to_string(),
+            identifiers: identifiers.into_iter().map(String::from).collect(),
+            reads: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_goodreads_csv() {
+        let content = "\
+Book Id,Title,Author,ISBN,ISBN13,My Rating,Date Read,Date Added,Exclusive Shelf
+1,\"Leviathan Wakes (The Expanse, #1)\",James S.A. Corey,\"=\"\"0316129089\"\"\",\"=\"\"9780316129084\"\"\",5,2023/05/14,2023/04/01,read
+2,Caliban's War,James S.A. Corey,\"=\"\"\"\"\",\"=\"\"\"\"\",0,,2023/04/01,to-read
+3,Abaddon's Gate,James S.A. Corey,,,0,,2023/04/01,read
+";
+        let rows = parse_csv(content).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(rows[0].title, "Leviathan Wakes (The Expanse, #1)");
+        assert_eq!(rows[0].identifiers, vec!["0316129089", "9780316129084"]);
+        assert_eq!(
+            rows[0].reads,
+            vec![(None, Some(String::from("2023-05-14")))]
+        );
+        assert_eq!(rows[1].identifiers, Vec::<String>::new());
+        assert_eq!(rows[1].reads, vec![(None, None)]);
+    }
+
+    #[test]
+    fn test_parse_storygraph_csv() {
+        let content = "\
+Title,Authors,ISBN/UID,Format,Read Status,Last Date Read,Dates Read,Read Count
+Leviathan Wakes,James S. A. Corey,B0047Y171G,digital,read,2024/02/10,\"2023/05/01-2023/05/14, 2024/02/01-2024/02/10\",2
+Caliban's War,James S. A. Corey,,digital,currently-reading,,,0
+Abaddon's Gate,James S. A. Corey,,digital,read,2023/08/01,,1
+";
+        let rows = parse_csv(content).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].identifiers, vec!["B0047Y171G"]);
+        assert_eq!(
+            rows[0].reads,
+            vec![
+                (
+                    Some(String::from("2023-05-01")),
+                    Some(String::from("2023-05-14"))
+                ),
+                (
+                    Some(String::from("2024-02-01")),
+                    Some(String::from("2024-02-10"))
+                ),
+            ]
+        );
+        assert_eq!(
+            rows[1].reads,
+            vec![(None, Some(String::from("2023-08-01")))]
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_csv() {
+        assert!(parse_csv("Title,Author\nSome,One\n").is_err());
+    }
+
+    #[test]
+    fn test_find_book() {
+        let books = [
+            book(
+                "B0047Y171G",
+                "Leviathan Wakes (The Expanse Book 1)",
+                "James S. A. Corey",
+            ),
+            book("B004XWJ3PW", "Caliban's War", "James S. A. Corey"),
+            book("B00000000X", "Caliban's War", "Someone Else"),
+        ];
+
+        let found = find_book(&books, &row("Whatever", "Whoever", vec!["b0047y171g"]));
+        assert_eq!(found.map(|book| book.asin.as_str()), Some("B0047Y171G"));
+
+        let found = find_book(
+            &books,
+            &row(
+                "Leviathan Wakes (The Expanse, #1)",
+                "James S.A. Corey",
+                vec![],
+            ),
+        );
+        assert_eq!(found.map(|book| book.asin.as_str()), Some("B0047Y171G"));
+
+        let found = find_book(&books, &row("Calibans War", "James Corey", vec![]));
+        assert_eq!(found.map(|book| book.asin.as_str()), Some("B004XWJ3PW"));
+
+        assert!(find_book(&books, &row("Leviathan Falls", "James S.A. Corey", vec![])).is_none());
+        assert!(find_book(&books, &row("Caliban's War", "", vec![])).is_none());
+    }
+
+    #[test]
+    fn test_get_similarity() {
+        assert_eq!(get_similarity("abc", "abc"), 1.0);
+        assert_eq!(get_similarity("abc", "xyz"), 0.0);
+        assert_eq!(get_similarity("", "abc"), 0.0);
+        assert!(get_similarity("calibans war", "caliban s war") > MIN_TITLE_SIMILARITY);
+    }
+}
@@ -0,0 +1,191 @@
+use regex::Regex;
+use serde::Serialize;
+use sqlx::Connection;
+use ts_rs::TS;
+
+use crate::common::{now, TS_FILE};
+use crate::crypto::{generate_token, hash_token};
+use crate::database::Database;
+use crate::user::Role;
+
+#[derive(sqlx::FromRow, Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct Invite {
+    pub id: i32,
+    pub role: Role,
+    pub created_by: Option<String>,
+    #[ts(as = "i32")]
+    pub time_created: i64,
+    #[ts(as = "i32")]
+    pub time_expires: i64,
+    pub used_by: Option<String>,
+    #[ts(as = "Option<i32>")]
+    pub time_used: Option<i64>,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetAllInvitesResult {
+    pub invites: Vec<Invite>,
+}
+
+/* Code is only returned here, for the admin to pass on to whoever is invited */
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct CreateInviteResult {
+    pub invite: Invite,
+    pub code: String,
+}
+
+impl Invite {
+    pub async fn create(
+        db: &Database,
+        role: Role,
+        created_by: Option<&str>,
+        valid_days: u32,
+    ) -> anyhow::Result<CreateInviteResult> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let code = generate_token();
+        let code_hash = hash_token(&code)?;
+        let time_created = now();
+        let time_expires = time_created + (valid_days as i64) * 24 * 60 * 60 * 1000;
+
+        let invite = sqlx::query_as::<_, Invite>(
+            "INSERT INTO invites (code_hash, role, created_by, time_created, time_expires)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            RETURNING id, role, created_by, time_created, time_expires, used_by, time_used",
+        )
+        .bind(code_hash)
+        .bind(role)
+        .bind(created_by)
+        .bind(time_created)
+        .bind(time_expires)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(CreateInviteResult {
+            invite: invite,
+            code: code,
+        })
+    }
+
+    pub async fn fetch_all(db: &Database) -> anyhow::Result<GetAllInvitesResult> {
+        let mut conn = db.acquire_db_conn().await?;
+        let invites = sqlx::query_as::<_, Invite>(
+            "SELECT id, role, created_by, time_created, time_expires, used_by, time_used
+            FROM invites ORDER BY time_created DESC",
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(GetAllInvitesResult { invites: invites })
+    }
+
+    pub async fn delete_by_id(db: &Database, id: i32) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!("DELETE FROM invites WHERE id = ?1", id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /* Uses up the invite and creates the account in single transaction, so that
+    neither can happen without the other. Returns None if the code is not valid.
+    Account is created first, as used_by has to point at existing user */
+    pub async fn register(
+        db: &Database,
+        code: &str,
+        username: &str,
+        pwhash: &str,
+    ) -> anyhow::Result<Option<Role>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        let code_hash = hash_token(code)?;
+        let time_now = now();
+        let role = sqlx::query_scalar::<_, Role>(
+            "SELECT role FROM invites
+            WHERE code_hash = ?1 AND time_used IS NULL AND time_expires > ?2",
+        )
+        .bind(&code_hash)
+        .bind(time_now)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let role = match role {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        sqlx::query!(
+            "INSERT INTO credentials (username, pwhash, role) VALUES (?1, ?2, ?3)",
+            username,
+            pwhash,
+            role,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // someone else could have used the code in the meantime, account is rolled back then
+        let result = sqlx::query!(
+            "UPDATE invites SET used_by = ?1, time_used = ?2
+            WHERE code_hash = ?3 AND time_used IS NULL",
+            username,
+            time_now,
+            code_hash,
+        )
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() != 1 {
+            return Ok(None);
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(role))
+    }
+}
+
+pub fn looks_like_username(username: &str) -> bool {
+    let re = Regex::new(r"^[A-Za-z0-9_.-]{1,32}$").unwrap();
+
+    re.is_match(username)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_looks_like_username() {
+        assert!(looks_like_username("mom"));
+        assert!(looks_like_username("john.doe-2"));
+
+        assert!(!looks_like_username(""));
+        assert!(!looks_like_username(" mom "));
+        assert!(!looks_like_username("<script>"));
+        assert!(!looks_like_username(&"a".repeat(33)));
+    }
+
+    #[tokio::test]
+    async fn test_register() {
+        let db = Database::init_for_tests().await.unwrap();
+        let created = Invite::create(&db, Role::ReadOnly, None, 7).await.unwrap();
+
+        let role = Invite::register(&db, &created.code, "newbie", "pwhash")
+            .await
+            .unwrap();
+        assert_eq!(role, Some(Role::ReadOnly));
+
+        let invites = Invite::fetch_all(&db).await.unwrap().invites;
+        assert_eq!(invites[0].used_by.as_deref(), Some("newbie"));
+
+        // single use
+        let role = Invite::register(&db, &created.code, "other", "pwhash")
+            .await
+            .unwrap();
+        assert_eq!(role, None);
+    }
+}
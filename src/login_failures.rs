@@ -0,0 +1,152 @@
+use sqlx::FromRow;
+
+use crate::common::now;
+use crate::database::Database;
+
+// failures older than this are forgotten, instead of counting towards the lockout
+const FAILURE_WINDOW_MS: i64 = 24 * 60 * 60 * 1000;
+const MAX_DELAY_SECONDS: u64 = 16;
+const LOCKOUT_THRESHOLD: i64 = 10;
+const LOCKOUT_MS: i64 = 15 * 60 * 1000;
+
+/* Failed logins are tracked separately by username, to protect single account
+from being guessed, and by client address, to slow down guessing across accounts */
+#[derive(Clone, Copy, Debug)]
+pub enum FailureKind {
+    Username,
+    Ip,
+}
+
+impl FailureKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FailureKind::Username => "username",
+            FailureKind::Ip => "ip",
+        }
+    }
+}
+
+#[derive(Debug, FromRow)]
+pub struct LoginFailures {
+    pub failure_count: i64,
+    pub time_last_failure: i64,
+    pub time_locked_until: Option<i64>,
+}
+
+impl LoginFailures {
+    pub async fn fetch(
+        db: &Database,
+        kind: FailureKind,
+        key: &str,
+    ) -> anyhow::Result<Option<LoginFailures>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query_as::<_, LoginFailures>(
+            "SELECT failure_count, time_last_failure, time_locked_until
+            FROM login_failures WHERE kind = ?1 AND key = ?2",
+        )
+        .bind(kind.as_str())
+        .bind(key)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(result)
+    }
+
+    pub fn is_locked(&self, time_now: i64) -> bool {
+        match self.time_locked_until {
+            Some(time_locked_until) => time_now < time_locked_until,
+            None => false,
+        }
+    }
+
+    /* Records failed attempt and returns how long the caller should wait before
+    responding, so that each consecutive guess gets slower. Counted in single
+    statement, so that parallel attempts can't overwrite each other's increments */
+    pub async fn record(db: &Database, kind: FailureKind, key: &str) -> anyhow::Result<u64> {
+        let time_now = now();
+        let mut conn = db.acquire_db_conn().await?;
+        let kind = kind.as_str();
+        let failure_count = sqlx::query_scalar::<_, i64>(
+            "INSERT INTO login_failures (kind, key, failure_count, time_last_failure)
+            VALUES (?1, ?2, 1, ?3)
+            ON CONFLICT (kind, key) DO UPDATE SET
+            failure_count = CASE
+              WHEN ?3 - time_last_failure < ?4 THEN failure_count + 1
+              ELSE 1
+            END,
+            time_last_failure = ?3
+            RETURNING failure_count",
+        )
+        .bind(kind)
+        .bind(key)
+        .bind(time_now)
+        .bind(FAILURE_WINDOW_MS)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if failure_count >= LOCKOUT_THRESHOLD {
+            let time_locked_until = time_now + LOCKOUT_MS;
+            sqlx::query!(
+                "UPDATE login_failures SET time_locked_until = ?1 WHERE kind = ?2 AND key = ?3",
+                time_locked_until,
+                kind,
+                key,
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(get_delay_seconds(failure_count))
+    }
+
+    /* Returns false if there was nothing to clear */
+    pub async fn clear(db: &Database, kind: FailureKind, key: &str) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let kind = kind.as_str();
+        let result = sqlx::query!(
+            "DELETE FROM login_failures WHERE kind = ?1 AND key = ?2",
+            kind,
+            key,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn get_delay_seconds(failure_count: i64) -> u64 {
+    if failure_count <= 1 {
+        return 0;
+    }
+
+    let exponent = (failure_count - 2).min(8) as u32;
+
+    (1u64 << exponent).min(MAX_DELAY_SECONDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_delay_seconds() {
+        assert_eq!(get_delay_seconds(1), 0);
+        assert_eq!(get_delay_seconds(2), 1);
+        assert_eq!(get_delay_seconds(3), 2);
+        assert_eq!(get_delay_seconds(5), 8);
+        assert_eq!(get_delay_seconds(100), MAX_DELAY_SECONDS);
+    }
+
+    #[test]
+    fn test_is_locked() {
+        let failures = LoginFailures {
+            failure_count: LOCKOUT_THRESHOLD,
+            time_last_failure: 0,
+            time_locked_until: Some(LOCKOUT_MS),
+        };
+
+        assert!(failures.is_locked(LOCKOUT_MS - 1));
+        assert!(!failures.is_locked(LOCKOUT_MS));
+    }
+}
@@ -2,32 +2,60 @@
 extern crate rocket;
 
 use clap::{Parser, Subcommand};
+use rocket::fairing::AdHoc;
 use rocket::fs::{relative, FileServer};
 use std::env;
+use std::net::IpAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
+mod audit_log;
+mod backup;
 mod books;
 mod common;
+mod consistency;
 mod controllers;
 mod credentials;
 mod crypto;
 mod database;
 mod gatekeeper;
 mod genjs;
+mod goals;
+mod import;
+mod invites;
+mod login_failures;
+mod password_resets;
 mod passwords;
+mod proxy_auth;
+mod queue;
+mod ratings;
 mod reads;
 mod response;
 mod scraper;
 mod series;
+mod sessions;
+mod stats;
 mod subscriptions;
+mod tokens;
+mod totp;
 mod user;
+mod user_data;
 
+use crate::backup::BackupSettings;
 use crate::controllers::series::enqueue_all_series;
 use crate::crypto::init_crypto;
 use crate::database::Database;
 use crate::gatekeeper::GateKeeper;
 use crate::passwords::Command as PasswordsCommand;
+use crate::proxy_auth::ProxyAuthSettings;
+use crate::scraper::job::JobPriority;
 use crate::scraper::server::JobServer;
+use crate::sessions::SessionSettings;
+use crate::totp::TotpSettings;
+use crate::user::Role;
+
+const MAX_BACKUP_INTERVAL_H: u64 = 365 * 24;
 
 #[derive(Parser)]
 #[command(about)]
@@ -38,9 +66,33 @@ struct Args {
 
 #[derive(Subcommand)]
 enum Command {
+    /// Writes consistent snapshot of the database, safe to run while server is running
+    Backup {
+        /// directory for the snapshots
+        #[clap(long, default_value = "db/backups")]
+        dir: PathBuf,
+
+        /// how many newest snapshots to keep, older ones are removed
+        #[clap(long, default_value_t = 7)]
+        keep: usize,
+    },
+
+    /// Replaces the database with the snapshot. Stop the server first
+    Restore { file: PathBuf },
+
+    /// Reports rows pointing at series, books or users that no longer exist
+    CheckDb {
+        /// remove the orphaned rows, or clear the reference where it's optional
+        #[clap(long)]
+        repair: bool,
+    },
+
     /// Generate TypeScript bindings for structs annottated with TS macros
     Genjs {},
 
+    /// Imports read history of the user from Goodreads or StoryGraph CSV export
+    Import { username: String, file: PathBuf },
+
     /// Manage users and passwords
     Passwords {
         #[command(subcommand)]
@@ -52,15 +104,69 @@ enum Command {
         /// how frequently server should wake up to check for jobs to process
         #[clap(long, default_value_t = 30)]
         poll_interval_s: u64,
+
+        /// how long job in flight can keep running after shutdown was requested,
+        /// before it is aborted and put back in the queue
+        #[clap(long, default_value_t = 60)]
+        shutdown_timeout_s: u64,
+
+        /// how long session stays valid without any requests
+        #[clap(long, default_value_t = 336)]
+        session_idle_timeout_h: u64,
+
+        /// how long session stays valid since login, regardless of use
+        #[clap(long, default_value_t = 90)]
+        session_max_age_d: u64,
+
+        /// header with username set by authenticating reverse proxy, e.g. X-Remote-User.
+        /// When set, password login is disabled
+        #[clap(long)]
+        auth_header: Option<String>,
+
+        /// address of the proxy allowed to set auth header and X-Real-IP, can be repeated
+        #[clap(long = "trusted-proxy")]
+        trusted_proxies: Vec<IpAddr>,
+
+        /// role of users created on their first request through the proxy
+        #[clap(long, value_enum, default_value = "member")]
+        auth_header_default_role: Role,
+
+        /// require login even for browsing series and upcoming books
+        #[clap(long)]
+        private: bool,
+
+        /// back up the database every this many hours (up to a year), disabled if not set
+        #[clap(long, value_parser = clap::value_parser!(u64).range(1..=MAX_BACKUP_INTERVAL_H))]
+        backup_interval_h: Option<u64>,
+
+        /// directory for the scheduled backups
+        #[clap(long, default_value = "db/backups")]
+        backup_dir: PathBuf,
+
+        /// how many newest scheduled backups to keep
+        #[clap(long, default_value_t = 7)]
+        backup_keep: usize,
     },
 }
 
-fn spawn_thread_for_daily_scrape(database: Arc<Database>) {
-    let user = None;
+fn spawn_thread_for_daily_scrape(job_server: Arc<JobServer>) {
+    let username = None;
     tokio::spawn(async move {
         loop {
             common::sleep_seconds(86400).await;
-            let _ = enqueue_all_series(&database, user).await;
+            let _ = enqueue_all_series(&job_server, JobPriority::Scheduled, username).await;
+        }
+    });
+}
+
+fn spawn_thread_for_backups(database: Arc<Database>, settings: BackupSettings, interval_h: u64) {
+    tokio::spawn(async move {
+        loop {
+            common::sleep_seconds(interval_h * 3600).await;
+            match backup::create_backup(&database, &settings).await {
+                Ok(path) => log::info!("Database backed up to {}", path.display()),
+                Err(error) => log::error!("Database backup failed: {:?}", error),
+            };
         }
     });
 }
@@ -73,20 +179,65 @@ async fn main() -> anyhow::Result<()> {
 
     let args = Args::parse();
     match args.command {
+        Command::Backup { dir, keep } => {
+            let settings = BackupSettings::new(dir, keep)?;
+            let database = Database::init().await?;
+            let path = backup::create_backup(&database, &settings).await?;
+            println!("Database backed up to {}", path.display());
+        }
+
+        Command::Restore { file } => {
+            let previous_path = backup::restore_backup(&file).await?;
+            println!(
+                "Database restored from {}, previous one was kept as {}",
+                file.display(),
+                previous_path.display()
+            );
+        }
+
+        Command::CheckDb { repair } => {
+            let database = Database::init().await?;
+            consistency::check_database(database, repair).await;
+        }
+
         Command::Genjs {} => {
             genjs::export_js_types();
         }
 
+        Command::Import { username, file } => {
+            let database = Database::init().await?;
+            import::import_file(database, username, file).await;
+        }
+
         Command::Passwords { command } => {
-            let database = Database::init().await;
+            let database = Database::init().await?;
             passwords::manage_passwords(database, command).await;
         }
 
-        Command::Server { poll_interval_s } => {
-            let database = Arc::new(Database::init().await);
+        Command::Server {
+            poll_interval_s,
+            shutdown_timeout_s,
+            session_idle_timeout_h,
+            session_max_age_d,
+            auth_header,
+            trusted_proxies,
+            auth_header_default_role,
+            private,
+            backup_interval_h,
+            backup_dir,
+            backup_keep,
+        } => {
+            let proxy_auth_settings =
+                ProxyAuthSettings::new(auth_header, trusted_proxies, auth_header_default_role)?;
+            let backup_settings = BackupSettings::new(backup_dir, backup_keep)?;
+            let totp_settings = TotpSettings::from_env()?;
+            let database = Arc::new(Database::init().await?);
             let job_server = JobServer::init(database.clone(), poll_interval_s);
 
-            spawn_thread_for_daily_scrape(database.clone());
+            spawn_thread_for_daily_scrape(job_server.clone());
+            if let Some(interval_h) = backup_interval_h {
+                spawn_thread_for_backups(database.clone(), backup_settings, interval_h);
+            }
 
             let _rocket = rocket::build()
                 .mount(
@@ -100,14 +251,43 @@ async fn main() -> anyhow::Result<()> {
                 .mount(
                     "/api",
                     routes![
+                        controllers::accounts::change_password,
+                        controllers::accounts::reset_password,
+                        controllers::accounts::get_all_users,
+                        controllers::accounts::create_user,
+                        controllers::accounts::create_password_reset,
+                        controllers::accounts::delete_user,
+                        controllers::audit::get_filtered,
                         controllers::books::get_all,
-                        controllers::books::mark_read,
-                        controllers::books::mark_read_on_date,
-                        controllers::books::mark_unread,
+                        controllers::books::add_reading,
+                        controllers::books::update_reading,
+                        controllers::books::remove_reading,
+                        controllers::books::set_rating,
+                        controllers::goals::get_all,
+                        controllers::goals::set,
+                        controllers::goals::remove,
+                        controllers::import::import,
+                        controllers::invites::get_all,
+                        controllers::invites::create,
+                        controllers::invites::remove,
+                        controllers::invites::register,
                         controllers::jobs::get_all,
+                        controllers::jobs::events,
+                        controllers::jobs::get_server_health,
+                        controllers::jobs::restart_server,
                         controllers::login::me,
                         controllers::login::login,
+                        controllers::login::login_totp,
                         controllers::login::logout,
+                        controllers::login::get_sessions,
+                        controllers::login::revoke_session,
+                        controllers::login::revoke_other_sessions,
+                        controllers::public::get_all_series,
+                        controllers::public::get_series,
+                        controllers::public::get_upcoming,
+                        controllers::queue::get_queue,
+                        controllers::queue::set_order,
+                        controllers::queue::set_pinned,
                         controllers::series::get_all,
                         controllers::series::scrape_all,
                         controllers::series::add,
@@ -116,12 +296,37 @@ async fn main() -> anyhow::Result<()> {
                         controllers::series::unsubscribe,
                         controllers::series::skip,
                         controllers::series::unskip,
+                        controllers::stats::get_stats,
+                        controllers::tokens::get_all,
+                        controllers::tokens::create,
+                        controllers::tokens::remove,
+                        controllers::totp::get_status,
+                        controllers::totp::enroll,
+                        controllers::totp::confirm,
+                        controllers::totp::regenerate_recovery_codes,
+                        controllers::totp::disable,
+                        controllers::user_data::export,
+                        controllers::user_data::import,
                     ],
                 )
                 .mount("/static", FileServer::from(relative!("www/static")))
                 .manage(database)
-                .manage(job_server)
-                .attach(GateKeeper {})
+                .manage(job_server.clone())
+                .manage(SessionSettings::new(
+                    session_idle_timeout_h,
+                    session_max_age_d,
+                ))
+                .manage(totp_settings)
+                .manage(proxy_auth_settings)
+                .attach(GateKeeper {
+                    public_browsing: !private,
+                })
+                .attach(AdHoc::on_shutdown("Stop job server", move |_| {
+                    Box::pin(async move {
+                        let grace_period = Duration::new(shutdown_timeout_s, 0);
+                        job_server.shutdown(grace_period).await;
+                    })
+                }))
                 .launch()
                 .await?;
         }
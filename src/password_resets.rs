@@ -0,0 +1,118 @@
+use serde::Serialize;
+use sqlx::Connection;
+use ts_rs::TS;
+
+use crate::common::{now, TS_FILE};
+use crate::crypto::{generate_token, hash_token};
+use crate::database::Database;
+
+const RESET_VALIDITY_MS: i64 = 48 * 60 * 60 * 1000;
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct CreatePasswordResetResult {
+    pub username: String,
+    pub token: String,
+    #[ts(as = "i32")]
+    pub time_expires: i64,
+}
+
+pub struct PasswordReset {}
+
+impl PasswordReset {
+    /* Creates single use token allowing to set new password for the user. Any
+    token created previously for the same user stops being valid */
+    pub async fn create(
+        db: &Database,
+        username: &str,
+    ) -> anyhow::Result<CreatePasswordResetResult> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        let token = generate_token();
+        let token_hash = hash_token(&token)?;
+        let time_created = now();
+        let time_expires = time_created + RESET_VALIDITY_MS;
+
+        sqlx::query!("DELETE FROM password_resets WHERE username = ?1", username)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            "INSERT INTO password_resets (token_hash, username, time_created, time_expires)
+            VALUES (?1, ?2, ?3, ?4)",
+            token_hash,
+            username,
+            time_created,
+            time_expires,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(CreatePasswordResetResult {
+            username: username.to_string(),
+            token: token,
+            time_expires: time_expires,
+        })
+    }
+
+    /* Returns username the token was created for, if it's still valid. Token is
+    not used up, so that the user can retry after choosing too weak password */
+    pub async fn fetch_username(db: &Database, token: &str) -> anyhow::Result<Option<String>> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let token_hash = hash_token(token)?;
+        let time_now = now();
+        let username = sqlx::query_scalar::<_, String>(
+            "SELECT username FROM password_resets WHERE token_hash = ?1 AND time_expires > ?2",
+        )
+        .bind(token_hash)
+        .bind(time_now)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(username)
+    }
+
+    /* Consumes the token and sets the new password in single transaction, so that
+    neither can happen without the other. Returns username the token was created for,
+    or None if it's not valid anymore */
+    pub async fn redeem(
+        db: &Database,
+        token: &str,
+        pwhash: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        let token_hash = hash_token(token)?;
+        let time_now = now();
+        let username = sqlx::query_scalar::<_, String>(
+            "DELETE FROM password_resets WHERE token_hash = ?1 AND time_expires > ?2
+            RETURNING username",
+        )
+        .bind(token_hash)
+        .bind(time_now)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let username = match username {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        sqlx::query!(
+            "UPDATE credentials SET pwhash = ?1 WHERE username = ?2",
+            pwhash,
+            username,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(username))
+    }
+}
@@ -1,9 +1,16 @@
+use chrono::DateTime;
 use clap::Subcommand;
 use std::io;
 
 use crate::credentials::Credentials;
 use crate::crypto::{hash_password, verify_password};
 use crate::database::Database;
+use crate::invites::Invite;
+use crate::login_failures::{FailureKind, LoginFailures};
+use crate::sessions::Session;
+use crate::tokens::ApiToken;
+use crate::totp::Totp;
+use crate::user::Role;
 
 #[derive(Subcommand)]
 pub enum Command {
@@ -13,6 +20,38 @@ pub enum Command {
     Remove { username: String },
     /// Lists users with active credentials
     ListUsernames,
+    /// Changes role of the user
+    Grant {
+        username: String,
+        #[arg(value_enum)]
+        role: Role,
+    },
+    /// Takes away admin role, turning user back into regular member
+    Revoke { username: String },
+    /// Creates named API token for the user, and prints it
+    CreateToken {
+        username: String,
+        name: String,
+        /// token will only allow reads, regardless of user role
+        #[arg(long)]
+        read_only: bool,
+    },
+    /// Lists API tokens of the user
+    ListTokens { username: String },
+    /// Removes API token, so it can no longer be used
+    RevokeToken { username: String, name: String },
+    /// Clears failed login attempts of the user, lifting the lockout
+    Unlock { username: String },
+    /// Turns off 2FA for user who lost their device and recovery codes
+    ResetTotp { username: String },
+    /// Creates single-use invite code, that lets someone register on their own
+    Invite {
+        #[arg(value_enum, default_value = "member")]
+        role: Role,
+        /// how long the invite can be used for
+        #[arg(long, default_value_t = 7)]
+        valid_days: u32,
+    },
 }
 
 pub async fn manage_passwords(db: Database, command: Command) {
@@ -26,6 +65,34 @@ pub async fn manage_passwords(db: Database, command: Command) {
         Command::ListUsernames => {
             list_usernames(db).await;
         }
+        Command::Grant { username, role } => {
+            set_role(db, username, role, None).await;
+        }
+        Command::Revoke { username } => {
+            set_role(db, username, Role::Member, Some(Role::Admin)).await;
+        }
+        Command::CreateToken {
+            username,
+            name,
+            read_only,
+        } => {
+            create_token(db, username, name, read_only).await;
+        }
+        Command::ListTokens { username } => {
+            list_tokens(db, username).await;
+        }
+        Command::RevokeToken { username, name } => {
+            revoke_token(db, username, name).await;
+        }
+        Command::Unlock { username } => {
+            unlock(db, username).await;
+        }
+        Command::ResetTotp { username } => {
+            reset_totp(db, username).await;
+        }
+        Command::Invite { role, valid_days } => {
+            create_invite(db, role, valid_days).await;
+        }
     };
 }
 
@@ -53,8 +120,15 @@ async fn set_password(db: Database, username: String) {
         return;
     }
 
-    match save_credentials(db, username, hash).await {
-        Ok(_) => println!("Password successfully set."),
+    if let Err(e) = save_credentials(&db, &username, hash).await {
+        println!("Something went wrong: {}", e);
+        return;
+    }
+    println!("Password successfully set.");
+
+    // whoever knew the old password should not stay logged in
+    match Session::delete_by_username(&db, &username, None).await {
+        Ok(_) => println!("Logged out all sessions of user '{}'.", username),
         Err(e) => println!("Something went wrong: {}", e),
     }
 }
@@ -80,12 +154,12 @@ fn read_password() -> anyhow::Result<String> {
     Ok(trimmed)
 }
 
-async fn save_credentials(db: Database, username: String, pwhash: String) -> anyhow::Result<()> {
-    match Credentials::fetch_by_username(&db, &username).await {
-        Ok(None) => Credentials::create(&db, &username, &pwhash).await,
+async fn save_credentials(db: &Database, username: &str, pwhash: String) -> anyhow::Result<()> {
+    match Credentials::fetch_by_username(db, username).await {
+        Ok(None) => Credentials::create(db, username, &pwhash, Role::Member).await,
         Ok(Some(mut creds)) => {
             creds.pwhash = pwhash;
-            creds.update(&db).await
+            creds.update(db).await
         }
         Err(e) => Err(e),
     }
@@ -108,6 +182,114 @@ async fn list_usernames(db: Database) {
     };
 
     for creds in all {
-        println!("{}", creds.username);
+        println!("{} ({:?})", creds.username, creds.role);
+    }
+}
+
+/* Role is only changed if the user currently has the required one, so that revoking
+admin can't accidentally promote read-only user */
+async fn set_role(db: Database, username: String, role: Role, required_role: Option<Role>) {
+    let mut creds = match Credentials::fetch_by_username(&db, &username).await {
+        Ok(Some(value)) => value,
+        Ok(None) => {
+            println!("User '{}' does not exist", username);
+            return;
+        }
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    if required_role.is_some_and(|required_role| creds.role != required_role) {
+        println!(
+            "User '{}' is {:?}, role was not changed",
+            username, creds.role
+        );
+        return;
+    }
+
+    creds.role = role;
+    match creds.update(&db).await {
+        Ok(_) => println!("User '{}' is now {:?}", username, role),
+        Err(e) => println!("Something went wrong: {}", e),
+    };
+}
+
+async fn create_token(db: Database, username: String, name: String, read_only: bool) {
+    match Credentials::fetch_by_username(&db, &username).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            println!("User '{}' does not exist", username);
+            return;
+        }
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    match ApiToken::create(&db, &username, &name, read_only).await {
+        Ok(result) => {
+            println!("Token created. Store it now, it won't be shown again:");
+            println!("{}", result.secret);
+        }
+        Err(e) => println!("Something went wrong: {}", e),
+    };
+}
+
+async fn list_tokens(db: Database, username: String) {
+    let result = match ApiToken::fetch_by_username(&db, &username).await {
+        Ok(value) => value,
+        Err(e) => {
+            println!("Something went wrong: {}", e);
+            return;
+        }
+    };
+
+    for token in result.tokens {
+        let scope = if token.read_only { "read-only" } else { "full" };
+        let last_used = match token
+            .time_last_used
+            .and_then(DateTime::from_timestamp_millis)
+        {
+            Some(time) => format!("last used {}", time.format("%Y-%m-%d %H:%M")),
+            None => String::from("never used"),
+        };
+        println!("{} ({}, {})", token.name, scope, last_used);
     }
 }
+
+async fn revoke_token(db: Database, username: String, name: String) {
+    match ApiToken::delete_by_name(&db, &username, &name).await {
+        Ok(true) => println!("Revoked token '{}' of user '{}'", name, username),
+        Ok(false) => println!("User '{}' has no token named '{}'", username, name),
+        Err(e) => println!("Something went wrong: {}", e),
+    };
+}
+
+async fn unlock(db: Database, username: String) {
+    match LoginFailures::clear(&db, FailureKind::Username, &username).await {
+        Ok(true) => println!("Cleared failed logins of user '{}'", username),
+        Ok(false) => println!("User '{}' has no failed logins", username),
+        Err(e) => println!("Something went wrong: {}", e),
+    };
+}
+
+async fn reset_totp(db: Database, username: String) {
+    match Totp::delete_by_username(&db, &username).await {
+        Ok(true) => println!("Disabled 2FA of user '{}'", username),
+        Ok(false) => println!("User '{}' does not have 2FA enabled", username),
+        Err(e) => println!("Something went wrong: {}", e),
+    };
+}
+
+async fn create_invite(db: Database, role: Role, valid_days: u32) {
+    match Invite::create(&db, role, None, valid_days).await {
+        Ok(result) => {
+            println!("Invite created. Store it now, it won't be shown again:");
+            println!("{}", result.code);
+        }
+        Err(e) => println!("Something went wrong: {}", e),
+    };
+}
@@ -0,0 +1,136 @@
+use rocket::request::Request;
+use std::net::IpAddr;
+
+use crate::user::Role;
+
+/* Lets authenticating reverse proxy (e.g. SSO) in front of the server decide who the
+user is. Header is only trusted when request comes directly from one of the listed
+proxies, as anyone else could set it to any username they like */
+pub struct ProxyAuthSettings {
+    pub header: Option<String>,
+    pub trusted_proxies: Vec<IpAddr>,
+    // role of users created on their first request through the proxy
+    pub default_role: Role,
+}
+
+impl ProxyAuthSettings {
+    pub fn new(
+        header: Option<String>,
+        trusted_proxies: Vec<IpAddr>,
+        default_role: Role,
+    ) -> anyhow::Result<ProxyAuthSettings> {
+        if header.is_some() && trusted_proxies.is_empty() {
+            return Err(anyhow::anyhow!(
+                "At least one trusted proxy is needed to use auth header"
+            ));
+        }
+
+        Ok(ProxyAuthSettings {
+            header: header,
+            trusted_proxies: trusted_proxies,
+            default_role: default_role,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.header.is_some()
+    }
+
+    /* Address of the direct peer is used rather than client_ip, which comes from
+    X-Real-IP header and so can't be trusted either */
+    pub fn get_remote_user<'r>(&self, request: &'r Request<'_>) -> Option<&'r str> {
+        let header = self.header.as_ref()?;
+        let username = request.headers().get_one(header)?.trim();
+        if username.is_empty() {
+            return None;
+        }
+
+        let remote_ip = request.remote().map(|address| address.ip());
+        match remote_ip {
+            Some(ip) if self.is_trusted_proxy(ip) => Some(username),
+            _ => {
+                log::warn!(
+                    "Ignored {} header from untrusted address {:?}",
+                    header,
+                    remote_ip
+                );
+                None
+            }
+        }
+    }
+
+    /* X-Real-IP is only taken from trusted proxies, anyone else could send new value
+    with every request, e.g. to avoid login lockout */
+    pub fn get_client_ip(&self, request: &Request<'_>) -> Option<IpAddr> {
+        let remote_ip = request.remote().map(|address| address.ip());
+        self.select_client_ip(remote_ip, request.real_ip())
+    }
+
+    fn select_client_ip(
+        &self,
+        remote_ip: Option<IpAddr>,
+        real_ip: Option<IpAddr>,
+    ) -> Option<IpAddr> {
+        match remote_ip {
+            Some(ip) if self.is_trusted_proxy(ip) => real_ip.or(remote_ip),
+            _ => remote_ip,
+        }
+    }
+
+    fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        // proxy on the same host may connect over IPv4 address mapped into IPv6
+        let ip = match ip {
+            IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+                Some(v4) => IpAddr::V4(v4),
+                None => ip,
+            },
+            IpAddr::V4(_) => ip,
+        };
+
+        self.trusted_proxies.contains(&ip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_requires_trusted_proxy() {
+        let header = Some(String::from("X-Remote-User"));
+        assert!(ProxyAuthSettings::new(header.clone(), vec![], Role::Member).is_err());
+        assert!(ProxyAuthSettings::new(None, vec![], Role::Member).is_ok());
+
+        let proxies = vec!["127.0.0.1".parse().unwrap()];
+        assert!(ProxyAuthSettings::new(header, proxies, Role::Member).is_ok());
+    }
+
+    #[test]
+    fn test_is_trusted_proxy() {
+        let proxies = vec!["10.0.0.1".parse().unwrap()];
+        let settings = ProxyAuthSettings::new(None, proxies, Role::Member).unwrap();
+
+        assert!(settings.is_trusted_proxy("10.0.0.1".parse().unwrap()));
+        assert!(settings.is_trusted_proxy("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!settings.is_trusted_proxy("10.0.0.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_select_client_ip() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let client: IpAddr = "192.168.1.20".parse().unwrap();
+        let forged: IpAddr = "1.2.3.4".parse().unwrap();
+        let settings = ProxyAuthSettings::new(None, vec![proxy], Role::Member).unwrap();
+
+        assert_eq!(
+            settings.select_client_ip(Some(proxy), Some(client)),
+            Some(client)
+        );
+        assert_eq!(settings.select_client_ip(Some(proxy), None), Some(proxy));
+        assert_eq!(
+            settings.select_client_ip(Some(client), Some(forged)),
+            Some(client)
+        );
+        assert_eq!(settings.select_client_ip(None, Some(forged)), None);
+    }
+}
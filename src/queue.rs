@@ -0,0 +1,331 @@
+use rocket::form::FromFormField;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteConnection;
+use sqlx::Connection;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use ts_rs::TS;
+
+use crate::books::{Book, BookWithReadings};
+use crate::common::{now, today, TS_FILE};
+use crate::database::Database;
+use crate::reads::ReadingStatus;
+use crate::user::User;
+
+#[derive(sqlx::Type, FromFormField, Deserialize, Serialize, TS, Clone, Copy, Debug, PartialEq)]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export_to = TS_FILE)]
+pub enum QueueOrder {
+    // series that haven't been read for the longest time, never started ones last
+    #[field(value = "oldest_unfinished")]
+    OldestUnfinished,
+    // catch up on series before their next book comes out
+    #[field(value = "soonest_release")]
+    SoonestRelease,
+    // as pinned by the user, series that aren't pinned go last
+    #[field(value = "manual")]
+    Manual,
+}
+
+/* Next book of one series, with what the ordering is based on */
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct QueuedBook {
+    #[serde(flatten)]
+    #[ts(flatten)]
+    pub book: BookWithReadings,
+    // released, but unread books in the series, including this one
+    pub unread_count: u32,
+    pub last_read_date: Option<String>,
+    pub next_release_date: Option<String>,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetQueueResult {
+    pub order: QueueOrder,
+    // series asins, in the manual order
+    pub pinned: Vec<String>,
+    pub books: Vec<QueuedBook>,
+}
+
+pub async fn fetch_by_user(db: &Database, user: &User) -> anyhow::Result<GetQueueResult> {
+    let order = fetch_order(db, user).await?;
+    let pinned = fetch_pinned(db, user).await?;
+    let books = Book::fetch_by_user(db, user).await?.books;
+
+    Ok(GetQueueResult {
+        books: get_queue(books, &today(), order, &pinned),
+        order: order,
+        pinned: pinned,
+    })
+}
+
+pub async fn fetch_order(db: &Database, user: &User) -> anyhow::Result<QueueOrder> {
+    let mut conn = db.acquire_db_conn().await?;
+    let order = sqlx::query_scalar::<_, QueueOrder>(
+        "SELECT queue_order FROM queue_preferences WHERE username = ?1",
+    )
+    .bind(&user.username)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(order.unwrap_or(QueueOrder::OldestUnfinished))
+}
+
+pub async fn fetch_pinned(db: &Database, user: &User) -> anyhow::Result<Vec<String>> {
+    let mut conn = db.acquire_db_conn().await?;
+    let pinned = sqlx::query_scalar::<_, String>(
+        "SELECT series_asin FROM queue_pins WHERE username = ?1 ORDER BY position",
+    )
+    .bind(&user.username)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(pinned)
+}
+
+pub async fn set_order(db: &Database, user: &User, order: QueueOrder) -> anyhow::Result<()> {
+    let mut conn = db.acquire_db_conn().await?;
+    set_order_with_conn(&mut conn, user, order).await
+}
+
+pub async fn set_order_with_conn(
+    conn: &mut SqliteConnection,
+    user: &User,
+    order: QueueOrder,
+) -> anyhow::Result<()> {
+    let time_updated = now();
+    sqlx::query!(
+        "INSERT INTO queue_preferences (username, queue_order, time_updated)
+        VALUES (?1, ?2, ?3)
+        ON CONFLICT (username) DO UPDATE SET queue_order = ?2, time_updated = ?3",
+        user.username,
+        order,
+        time_updated,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/* Replaces the whole manual order, series left out are unpinned. Unknown series are
+skipped */
+pub async fn set_pinned(db: &Database, user: &User, series_asins: &[String]) -> anyhow::Result<()> {
+    let mut conn = db.acquire_db_conn().await?;
+    let mut tx = conn.begin().await?;
+    set_pinned_with_conn(&mut tx, user, series_asins).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/* For callers that already hold a connection, they take care of the transaction */
+pub async fn set_pinned_with_conn(
+    conn: &mut SqliteConnection,
+    user: &User,
+    series_asins: &[String],
+) -> anyhow::Result<()> {
+    sqlx::query!("DELETE FROM queue_pins WHERE username = ?1", user.username)
+        .execute(&mut *conn)
+        .await?;
+
+    for (position, series_asin) in series_asins.iter().enumerate() {
+        let position = position as i64;
+        sqlx::query!(
+            "INSERT OR IGNORE INTO queue_pins (username, series_asin, position)
+            SELECT ?1, ?2, ?3 WHERE EXISTS (SELECT 1 FROM series WHERE asin = ?2)",
+            user.username,
+            series_asin,
+            position,
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+fn is_read(book: &BookWithReadings) -> bool {
+    book.readings
+        .iter()
+        .any(|reading| reading.status == ReadingStatus::Read)
+}
+
+/* Lowest ordinal released, but unread book of each series. Series that are caught up
+with don't show up at all */
+fn get_queue(
+    books: Vec<BookWithReadings>,
+    today: &str,
+    order: QueueOrder,
+    pinned: &[String],
+) -> Vec<QueuedBook> {
+    let mut books_by_series: HashMap<String, Vec<BookWithReadings>> = HashMap::new();
+    for book in books {
+        books_by_series
+            .entry(book.book.series_asin.clone())
+            .or_default()
+            .push(book);
+    }
+
+    let mut queue: Vec<QueuedBook> = Vec::new();
+    for (_, mut books) in books_by_series {
+        books.sort_by_key(|book| book.book.ordinal);
+
+        // dates are all in the same format, so they compare as strings
+        let last_read_date = books
+            .iter()
+            .flat_map(|book| book.readings.iter())
+            .filter(|reading| reading.status == ReadingStatus::Read)
+            .filter_map(|reading| reading.finish_date.clone())
+            .max();
+        let next_release_date = books
+            .iter()
+            .filter_map(|book| book.book.release_date.clone())
+            .filter(|release_date| release_date.as_str() > today)
+            .min();
+
+        let mut unread = books.into_iter().filter(|book| {
+            let is_released = match &book.book.release_date {
+                Some(release_date) => release_date.as_str() <= today,
+                None => true,
+            };
+            is_released && !is_read(book)
+        });
+
+        if let Some(book) = unread.next() {
+            queue.push(QueuedBook {
+                book: book,
+                unread_count: 1 + unread.count() as u32,
+                last_read_date: last_read_date,
+                next_release_date: next_release_date,
+            });
+        }
+    }
+
+    let positions: HashMap<&str, usize> = pinned
+        .iter()
+        .enumerate()
+        .map(|(position, series_asin)| (series_asin.as_str(), position))
+        .collect();
+
+    queue.sort_by(|a, b| {
+        let ordering = match order {
+            QueueOrder::OldestUnfinished => compare_none_last(&a.last_read_date, &b.last_read_date),
+            QueueOrder::SoonestRelease => {
+                compare_none_last(&a.next_release_date, &b.next_release_date)
+                    .then_with(|| compare_none_last(&a.last_read_date, &b.last_read_date))
+            }
+            QueueOrder::Manual => compare_none_last(
+                &positions.get(a.book.book.series_asin.as_str()),
+                &positions.get(b.book.book.series_asin.as_str()),
+            ),
+        };
+        ordering.then_with(|| a.book.book.title.cmp(&b.book.book.title))
+    });
+
+    queue
+}
+
+fn compare_none_last<T: Ord>(a: &Option<T>, b: &Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reads::Reading;
+
+    fn book(series_asin: &str, ordinal: u32, release_date: Option<&str>) -> BookWithReadings {
+        BookWithReadings {
+            book: Book {
+                asin: format!("{}-{}", series_asin, ordinal),
+                series_asin: series_asin.to_string(),
+                ordinal: ordinal,
+                title: format!("{} {}", series_asin, ordinal),
+                author: String::from("Author"),
+                release_date: release_date.map(String::from),
+                time_first_seen: 0,
+            },
+            readings: Vec::new(),
+            rating: None,
+            note: None,
+        }
+    }
+
+    fn read(mut book: BookWithReadings, finish_date: &str) -> BookWithReadings {
+        book.readings.push(Reading {
+            id: 0,
+            username: String::from("user"),
+            book_asin: book.book.asin.clone(),
+            status: ReadingStatus::Read,
+            start_date: None,
+            finish_date: Some(finish_date.to_string()),
+            progress_percent: Some(100),
+            progress_location: None,
+            time_created: 0,
+            time_updated: 0,
+        });
+        book
+    }
+
+    fn library() -> Vec<BookWithReadings> {
+        vec![
+            // A: read a long time ago, next one coming out soon
+            read(book("A", 1, None), "2024-01-01"),
+            book("A", 2, None),
+            book("A", 3, Some("2025-03-01")),
+            // B: read recently, next one coming out later
+            read(book("B", 1, None), "2025-01-01"),
+            book("B", 2, Some("2025-01-10")),
+            book("B", 3, Some("2025-06-01")),
+            // C: never started
+            book("C", 2, None),
+            book("C", 1, None),
+            // D: all caught up
+            read(book("D", 1, None), "2024-06-01"),
+            book("D", 2, Some("2025-12-01")),
+        ]
+    }
+
+    fn get_asins(queue: &[QueuedBook]) -> Vec<&str> {
+        queue
+            .iter()
+            .map(|queued| queued.book.book.asin.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_get_queue_picks_next_book_of_each_series() {
+        let queue = get_queue(library(), "2025-02-01", QueueOrder::OldestUnfinished, &[]);
+
+        assert_eq!(get_asins(&queue), vec!["A-2", "B-2", "C-1"]);
+        assert_eq!(queue[0].unread_count, 1);
+        assert_eq!(queue[0].last_read_date.as_deref(), Some("2024-01-01"));
+        assert_eq!(queue[0].next_release_date.as_deref(), Some("2025-03-01"));
+        assert_eq!(queue[2].unread_count, 2);
+    }
+
+    #[test]
+    fn test_get_queue_order() {
+        let today = "2025-02-01";
+
+        let queue = get_queue(library(), today, QueueOrder::SoonestRelease, &[]);
+        assert_eq!(get_asins(&queue), vec!["A-2", "B-2", "C-1"]);
+
+        let pinned = [String::from("C"), String::from("B")];
+        let queue = get_queue(library(), today, QueueOrder::Manual, &pinned);
+        assert_eq!(get_asins(&queue), vec!["C-1", "B-2", "A-2"]);
+
+        // once A-3 is out, B's next release is the soonest one
+        let queue = get_queue(library(), "2025-03-01", QueueOrder::SoonestRelease, &[]);
+        assert_eq!(get_asins(&queue), vec!["B-2", "A-2", "C-1"]);
+    }
+}
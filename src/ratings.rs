@@ -0,0 +1,115 @@
+use sqlx::sqlite::SqliteConnection;
+
+use crate::common::now;
+use crate::database::Database;
+use crate::user::User;
+
+pub const MIN_RATING: u8 = 1;
+pub const MAX_RATING: u8 = 5;
+const MAX_NOTE_LENGTH: usize = 10000;
+
+/* Personal rating and notes, private to the user. Kept per book rather than per
+reading, re-read simply updates them */
+#[derive(sqlx::FromRow, Debug)]
+pub struct BookRating {
+    pub book_asin: String,
+    pub rating: Option<u8>,
+    pub note: Option<String>,
+}
+
+impl BookRating {
+    /* Clearing both rating and note removes the row altogether */
+    pub async fn set(
+        db: &Database,
+        user: &User,
+        book_asin: &str,
+        rating: Option<u8>,
+        note: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        BookRating::set_with_conn(&mut conn, user, book_asin, rating, note).await
+    }
+
+    pub async fn set_with_conn(
+        conn: &mut SqliteConnection,
+        user: &User,
+        book_asin: &str,
+        rating: Option<u8>,
+        note: Option<&str>,
+    ) -> anyhow::Result<()> {
+        if rating.is_none() && note.is_none() {
+            sqlx::query!(
+                "DELETE FROM book_ratings WHERE username = ?1 AND book_asin = ?2",
+                user.username,
+                book_asin,
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            return Ok(());
+        }
+
+        let time_updated = now();
+        sqlx::query!(
+            "INSERT INTO book_ratings (username, book_asin, rating, note, time_updated)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT (username, book_asin) DO UPDATE
+            SET rating = ?3, note = ?4, time_updated = ?5",
+            user.username,
+            book_asin,
+            rating,
+            note,
+            time_updated,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_by_user(db: &Database, user: &User) -> anyhow::Result<Vec<BookRating>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let ratings = sqlx::query_as::<_, BookRating>(
+            "SELECT book_asin, rating, note FROM book_ratings WHERE username = ?1",
+        )
+        .bind(&user.username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(ratings)
+    }
+}
+
+pub fn check_rating(rating: Option<u8>, note: Option<&str>) -> Result<(), String> {
+    if rating.is_some_and(|rating| !(MIN_RATING..=MAX_RATING).contains(&rating)) {
+        return Err(format!(
+            "Rating has to be between {} and {}!",
+            MIN_RATING, MAX_RATING
+        ));
+    }
+
+    if note.is_some_and(|note| note.chars().count() > MAX_NOTE_LENGTH) {
+        return Err(format!(
+            "Note can't be longer than {} characters!",
+            MAX_NOTE_LENGTH
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_rating() {
+        assert!(check_rating(None, None).is_ok());
+        assert!(check_rating(Some(1), Some("meh")).is_ok());
+        assert!(check_rating(Some(5), None).is_ok());
+
+        assert!(check_rating(Some(0), None).is_err());
+        assert!(check_rating(Some(6), None).is_err());
+        assert!(check_rating(None, Some(&"a".repeat(MAX_NOTE_LENGTH + 1))).is_err());
+    }
+}
@@ -1,57 +1,262 @@
-use serde::Serialize;
+use chrono::NaiveDate;
+use rocket::form::FromFormField;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteConnection;
+use ts_rs::TS;
 
+use crate::common::{now, TS_FILE};
 use crate::database::Database;
 use crate::user::User;
 
-#[derive(sqlx::FromRow, Serialize)]
-pub struct ReadState {
-    username: String,
-    book_asin: String,
+#[derive(
+    sqlx::Type, FromFormField, Deserialize, Serialize, TS, Clone, Copy, Debug, PartialEq, Eq, Hash,
+)]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export_to = TS_FILE)]
+pub enum ReadingStatus {
+    #[field(value = "want_to_read")]
+    WantToRead,
+    #[field(value = "reading")]
+    Reading,
+    #[field(value = "read")]
+    Read,
+    #[field(value = "abandoned")]
+    Abandoned,
 }
 
-impl ReadState {
-    pub async fn add(db: &Database, user: &User, book_asin: &str) -> anyhow::Result<()> {
-        let mut conn = db.acquire_db_conn().await?;
-        sqlx::query!(
-            "INSERT INTO read_state (username, book_asin) VALUES(?1, ?2)",
-            user.username,
-            book_asin,
-        )
-        .execute(&mut *conn)
-        .await?;
+/* One pass through the book. Re-reads are separate rows, so that history of the
+previous ones is kept */
+#[derive(sqlx::FromRow, Serialize, TS, Clone, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct Reading {
+    pub id: i32,
+    #[serde(skip)]
+    #[ts(skip)]
+    pub username: String,
+    pub book_asin: String,
+    pub status: ReadingStatus,
+    pub start_date: Option<String>,
+    pub finish_date: Option<String>,
+    pub progress_percent: Option<u32>,
+    // e.g. Kindle location or page, whichever the reader tracks
+    pub progress_location: Option<u32>,
+    #[ts(as = "i32")]
+    pub time_created: i64,
+    #[ts(as = "i32")]
+    pub time_updated: i64,
+}
+
+/* Everything user can set on the reading. Dates that are implied by the status are
+filled in with today, e.g. finished book without finish date */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadingParams {
+    pub status: ReadingStatus,
+    pub start_date: Option<String>,
+    pub finish_date: Option<String>,
+    pub progress_percent: Option<u32>,
+    pub progress_location: Option<u32>,
+}
+
+impl ReadingParams {
+    pub fn normalize(mut self, today: &str) -> Result<ReadingParams, String> {
+        for date in [&self.start_date, &self.finish_date].into_iter().flatten() {
+            if !is_valid_date(date) {
+                return Err(format!("'{}' is not a valid date!", date));
+            }
+        }
+
+        if self.progress_percent.is_some_and(|percent| percent > 100) {
+            return Err(String::from("Progress can't be over 100%!"));
+        }
+
+        match self.status {
+            ReadingStatus::WantToRead => {
+                self.start_date = None;
+                self.finish_date = None;
+            }
+            ReadingStatus::Reading => {
+                self.start_date = self.start_date.or(Some(today.to_string()));
+                self.finish_date = None;
+            }
+            ReadingStatus::Read => {
+                self.finish_date = self.finish_date.or(Some(today.to_string()));
+                self.progress_percent = Some(100);
+            }
+            ReadingStatus::Abandoned => {}
+        };
 
-        Ok(())
+        if let (Some(start_date), Some(finish_date)) = (&self.start_date, &self.finish_date) {
+            // dates are all in the same format, so they compare as strings
+            if finish_date < start_date {
+                return Err(String::from(
+                    "Book can't be finished before it was started!",
+                ));
+            }
+        }
+
+        Ok(self)
     }
+}
 
-    pub async fn remove(db: &Database, user: &User, book_asin: &str) -> anyhow::Result<()> {
+impl Reading {
+    pub async fn create(
+        db: &Database,
+        user: &User,
+        book_asin: &str,
+        params: &ReadingParams,
+    ) -> anyhow::Result<Reading> {
         let mut conn = db.acquire_db_conn().await?;
-        sqlx::query!(
-            "DELETE FROM read_state WHERE username = ?1 AND book_asin = ?2",
-            user.username,
-            book_asin,
+        Reading::create_with_conn(&mut conn, user, book_asin, params).await
+    }
+
+    pub async fn create_with_conn(
+        conn: &mut SqliteConnection,
+        user: &User,
+        book_asin: &str,
+        params: &ReadingParams,
+    ) -> anyhow::Result<Reading> {
+        let time_now = now();
+        let reading = sqlx::query_as::<_, Reading>(
+            "INSERT INTO readings (
+              username, book_asin, status, start_date, finish_date,
+              progress_percent, progress_location, time_created, time_updated
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)
+            RETURNING *",
         )
-        .execute(&mut *conn)
+        .bind(&user.username)
+        .bind(book_asin)
+        .bind(params.status)
+        .bind(&params.start_date)
+        .bind(&params.finish_date)
+        .bind(params.progress_percent)
+        .bind(params.progress_location)
+        .bind(time_now)
+        .fetch_one(&mut *conn)
         .await?;
 
-        Ok(())
+        Ok(reading)
     }
 
+    /* Returns None if there is no such reading, or it belongs to someone else */
     pub async fn update(
         db: &Database,
         user: &User,
-        book_asin: &str,
-        read_date: &str,
-    ) -> anyhow::Result<()> {
+        id: i32,
+        params: &ReadingParams,
+    ) -> anyhow::Result<Option<Reading>> {
         let mut conn = db.acquire_db_conn().await?;
-        sqlx::query!(
-            "UPDATE read_state SET read_date = ?1 WHERE username = ?2 AND book_asin = ?3",
-            read_date,
+
+        let reading = sqlx::query_as::<_, Reading>(
+            "UPDATE readings SET
+              status = ?1, start_date = ?2, finish_date = ?3,
+              progress_percent = ?4, progress_location = ?5, time_updated = ?6
+            WHERE id = ?7 AND username = ?8
+            RETURNING *",
+        )
+        .bind(params.status)
+        .bind(&params.start_date)
+        .bind(&params.finish_date)
+        .bind(params.progress_percent)
+        .bind(params.progress_location)
+        .bind(now())
+        .bind(id)
+        .bind(&user.username)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(reading)
+    }
+
+    pub async fn delete(db: &Database, user: &User, id: i32) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!(
+            "DELETE FROM readings WHERE id = ?1 AND username = ?2",
+            id,
             user.username,
-            book_asin,
         )
         .execute(&mut *conn)
         .await?;
 
-        Ok(())
+        Ok(result.rows_affected() > 0)
+    }
+
+    /* Newest first, so that the first reading of each book is the current one */
+    pub async fn fetch_by_user(db: &Database, user: &User) -> anyhow::Result<Vec<Reading>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let readings = sqlx::query_as::<_, Reading>(
+            "SELECT * FROM readings WHERE username = ?1
+            ORDER BY time_created DESC, id DESC",
+        )
+        .bind(&user.username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(readings)
+    }
+}
+
+pub fn is_valid_date(date: &str) -> bool {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(status: ReadingStatus) -> ReadingParams {
+        ReadingParams {
+            status: status,
+            start_date: None,
+            finish_date: None,
+            progress_percent: None,
+            progress_location: None,
+        }
+    }
+
+    #[test]
+    fn test_is_valid_date() {
+        assert!(is_valid_date("2025-01-02"));
+
+        assert!(!is_valid_date("2025-02-31"));
+        assert!(!is_valid_date(" 2025-01-02 "));
+        assert!(!is_valid_date("2025-01-02 some string"));
+        assert!(!is_valid_date("2025-01-02 09:15:46"));
+    }
+
+    #[test]
+    fn test_normalize_fills_in_implied_dates() {
+        let today = "2025-03-04";
+
+        let reading = params(ReadingStatus::Reading).normalize(today).unwrap();
+        assert_eq!(reading.start_date.as_deref(), Some(today));
+        assert_eq!(reading.finish_date, None);
+
+        let reading = params(ReadingStatus::Read).normalize(today).unwrap();
+        assert_eq!(reading.finish_date.as_deref(), Some(today));
+        assert_eq!(reading.progress_percent, Some(100));
+
+        let mut want = params(ReadingStatus::WantToRead);
+        want.start_date = Some(String::from("2025-01-01"));
+        let reading = want.normalize(today).unwrap();
+        assert_eq!(reading.start_date, None);
+    }
+
+    #[test]
+    fn test_normalize_rejects_invalid_params() {
+        let today = "2025-03-04";
+
+        let mut invalid_date = params(ReadingStatus::Reading);
+        invalid_date.start_date = Some(String::from("2025-02-31"));
+        assert!(invalid_date.normalize(today).is_err());
+
+        let mut invalid_percent = params(ReadingStatus::Reading);
+        invalid_percent.progress_percent = Some(101);
+        assert!(invalid_percent.normalize(today).is_err());
+
+        let mut finished_before_start = params(ReadingStatus::Read);
+        finished_before_start.start_date = Some(String::from("2025-02-01"));
+        finished_before_start.finish_date = Some(String::from("2025-01-01"));
+        assert!(finished_before_start.normalize(today).is_err());
     }
 }
@@ -8,11 +8,20 @@ use ts_rs::TS;
 pub enum ApiResponse {
     Success,
     Data { data: String },
+    Attachment(Attachment),
     NotFound,
     BadRequest { message: String },
+    TooManyRequests { message: String },
     ServerError { message: String },
 }
 
+/* File to be downloaded, rather than shown in the UI */
+pub struct Attachment {
+    pub content_type: ContentType,
+    pub filename: String,
+    pub data: String,
+}
+
 impl<'r> Responder<'r, 'static> for ApiResponse {
     fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
         match self {
@@ -26,6 +35,19 @@ impl<'r> Responder<'r, 'static> for ApiResponse {
                 .header(ContentType::JSON)
                 .ok(),
 
+            ApiResponse::Attachment(attachment) => {
+                let disposition = format!(
+                    "attachment; filename=\"{}\"",
+                    get_safe_filename(&attachment.filename)
+                );
+
+                Response::build_from(attachment.data.respond_to(req)?)
+                    .status(Status::Ok)
+                    .header(attachment.content_type)
+                    .raw_header("Content-Disposition", disposition)
+                    .ok()
+            }
+
             ApiResponse::NotFound => Response::build_from("{}".respond_to(req)?)
                 .status(Status::NotFound)
                 .header(ContentType::JSON)
@@ -48,6 +70,15 @@ impl<'r> Responder<'r, 'static> for ApiResponse {
                     .header(ContentType::new("application", "problem+json"))
                     .ok()
             }
+
+            ApiResponse::TooManyRequests { message } => {
+                let error = wrap_error(&message);
+
+                Response::build_from(error.respond_to(req)?)
+                    .status(Status::TooManyRequests)
+                    .header(ContentType::new("application", "problem+json"))
+                    .ok()
+            }
         }
     }
 }
@@ -76,3 +107,27 @@ fn wrap_error(error_message: &str) -> String {
 
     error.to_string()
 }
+
+/* Filename goes into header as it is, so anything that could end the quoted string
+or the header value is replaced */
+fn get_safe_filename(filename: &str) -> String {
+    filename
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || "._-".contains(c) {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_safe_filename() {
+        assert_eq!(get_safe_filename("bst-mom.json"), "bst-mom.json");
+        assert_eq!(get_safe_filename("bst-a\";b.csv"), "bst-a__b.csv");
+        assert_eq!(get_safe_filename("bst-\r\nmöm.csv"), "bst-__m_m.csv");
+    }
+}
@@ -0,0 +1,47 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+use ts_rs::TS;
+
+use crate::common::TS_FILE;
+
+// Events are only meant for live UI updates, so slow consumers are allowed to miss
+// some of them and resync by refetching /api/jobs.
+const EVENT_BUFFER_SIZE: usize = 64;
+
+#[derive(Serialize, TS, Clone, Debug)]
+#[serde(tag = "variant")]
+#[ts(export_to = TS_FILE)]
+pub enum JobEvent {
+    StatusChanged {
+        job_id: i32,
+        status: String,
+    },
+    SeriesProgress {
+        job_id: i32,
+        series_asin: String,
+        books_total: u32,
+        books_processed: u32,
+    },
+}
+
+#[derive(Clone)]
+pub struct JobEventBus {
+    sender: broadcast::Sender<JobEvent>,
+}
+
+impl JobEventBus {
+    pub fn new() -> JobEventBus {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+
+        JobEventBus { sender: sender }
+    }
+
+    pub fn publish(&self, event: JobEvent) {
+        // send only fails when nobody is listening, which is the common case
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.sender.subscribe()
+    }
+}
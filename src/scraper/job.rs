@@ -1,9 +1,26 @@
+use rocket::form::FromFormField;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
 use crate::common::now;
 use crate::database::Database;
-use crate::user::User;
+
+/* Lower value gets processed first. Stored as int in db, so that ordering in
+fetch_next can be done directly by the column */
+#[derive(sqlx::Type, FromFormField, Deserialize, Serialize, TS, Clone, Copy, Debug, PartialEq)]
+#[ts(export_to = "types.ts")]
+#[repr(i32)]
+pub enum JobPriority {
+    // triggered directly by user, who is likely waiting for the result
+    #[field(value = "interactive")]
+    Interactive = 0,
+    // periodic scrapes, that nobody is actively waiting for
+    #[field(value = "scheduled")]
+    Scheduled = 1,
+    // bulk jobs that should only run when nothing else is queued
+    #[field(value = "backfill")]
+    Backfill = 2,
+}
 
 #[derive(Deserialize, Serialize)]
 #[serde(tag = "variant")]
@@ -23,6 +40,7 @@ pub struct Job {
     pub status: String,
     pub errors: Option<String>,
     pub username: Option<String>,
+    pub priority: JobPriority,
 
     #[ts(as = "i32")]
     pub time_created: i64,
@@ -54,10 +72,11 @@ impl Job {
 
         // this function is only called from JobServer. If job is seen with PROCESSING
         // state, it means server closed mid-processing, so need to restart the job
-        // instead of fetching next QUEUED
+        // instead of fetching next QUEUED, regardless of priorities
         let job = sqlx::query_as::<_, Job>(
             "SELECT * FROM jobs WHERE status IN ('QUEUED', 'PROCESSING')
-                ORDER BY time_created ASC LIMIT 1",
+                ORDER BY status = 'PROCESSING' DESC, priority ASC, time_created ASC
+                LIMIT 1",
         )
         .fetch_optional(&mut *conn)
         .await?;
@@ -68,11 +87,13 @@ impl Job {
     pub async fn add(
         db: &Database,
         job_params: JobParams,
-        user: Option<&User>,
+        priority: JobPriority,
+        username: Option<&str>,
     ) -> anyhow::Result<i32> {
         let mut conn = db.acquire_db_conn().await?;
 
         let params = serde_json::to_string(&job_params)?;
+        let priority = priority as i32;
         let maybe_existing_job = sqlx::query!(
             "SELECT id FROM jobs WHERE status = 'QUEUED' AND params = ?1",
             params
@@ -80,20 +101,27 @@ impl Job {
         .fetch_optional(&mut *conn)
         .await?;
         if let Some(job) = maybe_existing_job {
+            // same job requested again with more urgency should not wait in line
+            // behind the rest of the original request
+            sqlx::query!(
+                "UPDATE jobs SET priority = MIN(priority, ?1) WHERE id = ?2",
+                priority,
+                job.id,
+            )
+            .execute(&mut *conn)
+            .await?;
+
             let job_id: i32 = job.id.try_into().unwrap();
             return Ok(job_id);
         };
 
         let time_created = now();
-        let username: Option<&str> = match user {
-            Some(user) => Some(&user.username),
-            None => None,
-        };
         let result = sqlx::query_scalar!(
-            "INSERT INTO jobs (status, params, username, time_created)
-                    VALUES ('QUEUED', ?1, ?2, ?3) RETURNING id",
+            "INSERT INTO jobs (status, params, username, priority, time_created)
+                    VALUES ('QUEUED', ?1, ?2, ?3, ?4) RETURNING id",
             params,
             username,
+            priority,
             time_created,
         )
         .fetch_one(&mut *conn)
@@ -103,6 +131,24 @@ impl Job {
         Ok(job_id)
     }
 
+    pub async fn mark_as_queued(&mut self, db: &Database) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let status = "QUEUED";
+        sqlx::query!(
+            "UPDATE jobs SET status = ?1, time_started = NULL WHERE id = ?2",
+            status,
+            self.id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        self.status = status.to_string();
+        self.time_started = None;
+
+        Ok(())
+    }
+
     pub async fn mark_as_processing(&mut self, db: &Database) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
 
@@ -1,5 +1,6 @@
 mod book;
 mod common;
+pub mod events;
 pub mod job;
 mod processor;
 mod series;
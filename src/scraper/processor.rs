@@ -4,15 +4,16 @@ use std::error::Error;
 use thirtyfour::prelude::*;
 
 use crate::books::Book;
-use crate::database::Database;
 use crate::scraper::book::{scrape_book_page, ScrapeBookPageResult};
+use crate::scraper::events::JobEvent;
 use crate::scraper::job::{Job, JobParams};
 use crate::scraper::series::{scrape_series_page, ScrapeSeriesPageResult};
-use crate::user::User;
+use crate::scraper::server::JobServer;
 
 const POST_CLICK_WAIT_SECONDS: u64 = 10;
 
-pub async fn process(db: &Database, job: &Job) -> anyhow::Result<()> {
+pub async fn process(job_server: &JobServer, job: &Job) -> anyhow::Result<()> {
+    let db = &job_server.database;
     let params = match serde_json::from_str::<JobParams>(&job.params) {
         Ok(params) => params,
         Err(_) => {
@@ -23,17 +24,9 @@ pub async fn process(db: &Database, job: &Job) -> anyhow::Result<()> {
     };
 
     match params {
-        JobParams::Book { asin, .. } => process_book(db, &asin).await?,
+        JobParams::Book { asin, .. } => process_book(job_server, &asin).await?,
         JobParams::Series { asin } => {
-            process_series(db, &asin).await?;
-
-            // TODO: figure out better way to pass user to child jobs
-            let user: Option<User> = match &job.username {
-                Some(username) => Some(User {
-                    username: username.to_string(),
-                }),
-                None => None,
-            };
+            process_series(job_server, job, &asin).await?;
 
             /* newly fetched books will have release date set, but ones previously
             released need dedicated scrape to fill in release date */
@@ -44,7 +37,9 @@ pub async fn process(db: &Database, job: &Job) -> anyhow::Result<()> {
                         asin: book.asin.to_string(),
                         parent: job.id,
                     };
-                    Job::add(db, params, user.as_ref()).await?;
+                    job_server
+                        .enqueue(params, job.priority, job.username.as_deref())
+                        .await?;
                 }
             }
         }
@@ -53,46 +48,54 @@ pub async fn process(db: &Database, job: &Job) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn process_series(db: &Database, asin: &str) -> anyhow::Result<()> {
+async fn process_series(job_server: &JobServer, job: &Job, asin: &str) -> anyhow::Result<()> {
+    let db = &job_server.database;
     let local_books: HashMap<String, Book> = Book::fetch_by_series_asin(db, asin)
         .await?
         .into_iter()
         .map(|book| (String::from(&book.asin), book))
         .collect();
 
-    let result = match set_up_webdriver_and_scrape_series_page(asin).await {
+    let result = match set_up_webdriver_and_scrape_series_page(job_server, asin).await {
         Ok(value) => value,
         Err(e) => return Err(anyhow::anyhow!(e)),
     };
 
     result.series.save(db).await?;
 
-    for remote_book in result.books.iter() {
+    let books_total: u32 = result.books.len().try_into()?;
+    for (index, remote_book) in result.books.iter().enumerate() {
         match local_books.get(&remote_book.asin) {
             Some(local_book) => {
-                if remote_book.release_date == local_book.release_date {
-                    continue;
-                }
-                if let Some(release_date) = &remote_book.release_date {
-                    Book::update_release_date(&db, &local_book.asin, &release_date).await?;
+                if remote_book.release_date != local_book.release_date {
+                    if let Some(release_date) = &remote_book.release_date {
+                        Book::update_release_date(&db, &local_book.asin, &release_date).await?;
+                    }
                 }
             }
             None => {
                 remote_book.save(db).await?;
             }
         }
+
+        job_server.events.publish(JobEvent::SeriesProgress {
+            job_id: job.id,
+            series_asin: asin.to_string(),
+            books_total: books_total,
+            books_processed: (index + 1).try_into()?,
+        });
     }
 
     Ok(())
 }
 
-async fn process_book(db: &Database, asin: &str) -> anyhow::Result<()> {
-    let release_date = match set_up_webdriver_and_scrape_book_page(asin).await {
+async fn process_book(job_server: &JobServer, asin: &str) -> anyhow::Result<()> {
+    let release_date = match set_up_webdriver_and_scrape_book_page(job_server, asin).await {
         Ok(result) => result.release_date,
         Err(e) => return Err(anyhow::anyhow!(e)),
     };
 
-    Book::update_release_date(&db, &asin, &release_date).await
+    Book::update_release_date(&job_server.database, &asin, &release_date).await
 }
 
 async fn get_webdriver() -> Result<WebDriver, WebDriverError> {
@@ -107,12 +110,15 @@ fn get_amazon_series_url(series_asin: &str) -> String {
 }
 
 async fn set_up_webdriver_and_scrape_series_page(
+    job_server: &JobServer,
     asin: &str,
 ) -> Result<ScrapeSeriesPageResult, Box<dyn Error + Send + Sync>> {
     let driver = get_webdriver().await?;
+    job_server.set_active_driver(Some(&driver)).await;
     let url = get_amazon_series_url(&asin);
     let result = scrape_series_page(&driver, url, asin, POST_CLICK_WAIT_SECONDS).await;
     // regardless wether parsing was sucessful or not, need to clean up the browser window we used
+    job_server.set_active_driver(None).await;
     driver.quit().await?;
 
     result
@@ -123,12 +129,15 @@ fn get_amazon_book_url(series_asin: &str) -> String {
 }
 
 async fn set_up_webdriver_and_scrape_book_page(
+    job_server: &JobServer,
     asin: &str,
 ) -> Result<ScrapeBookPageResult, Box<dyn Error + Send + Sync>> {
     let driver = get_webdriver().await?;
+    job_server.set_active_driver(Some(&driver)).await;
     let url = get_amazon_book_url(&asin);
     let result = scrape_book_page(&driver, url, POST_CLICK_WAIT_SECONDS).await;
     // regardless wether parsing was sucessful or not, need to clean up the browser window we used
+    job_server.set_active_driver(None).await;
     driver.quit().await?;
 
     result
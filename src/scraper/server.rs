@@ -1,44 +1,184 @@
-use std::sync::Arc;
-use tokio::sync::Semaphore;
+use serde::Serialize;
+use std::sync::{Arc, Mutex as SyncMutex};
+use std::time::Duration;
+use thirtyfour::WebDriver;
+use tokio::select;
+use tokio::sync::{watch, Mutex, Notify, Semaphore};
+use tokio::time::timeout;
+use ts_rs::TS;
 
-use crate::common::sleep_seconds;
+use crate::common::{sleep_seconds, TS_FILE};
 use crate::database::Database;
-use crate::scraper::job::Job;
+use crate::scraper::events::{JobEvent, JobEventBus};
+use crate::scraper::job::{Job, JobParams, JobPriority};
 use crate::scraper::processor;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ShutdownStage {
+    Running,
+    // no new jobs are picked up, but the one in flight is allowed to finish
+    Draining,
+    // job in flight should be dropped and put back in the queue
+    Aborting,
+}
+
+const BACKOFF_BASE_SECONDS: u64 = 5;
+const BACKOFF_MAX_SECONDS: u64 = 600;
+
+#[derive(Serialize, TS, Clone, Debug)]
+#[serde(tag = "status")]
+#[ts(export_to = TS_FILE)]
+pub enum JobServerHealth {
+    Running,
+    // polling loop hit errors that are expected to go away, and is retrying
+    Degraded {
+        reason: String,
+        consecutive_failures: u32,
+    },
+    // polling loop exited, and has to be restarted manually
+    Stopped {
+        reason: String,
+    },
+}
+
 pub struct JobServer {
     pub database: Arc<Database>,
+    pub events: JobEventBus,
+    poll_interval: u64,
+    health: SyncMutex<JobServerHealth>,
     processing_permit: Semaphore,
+    new_job_added: Notify,
+    shutdown_stage: watch::Sender<ShutdownStage>,
+    active_driver: Mutex<Option<WebDriver>>,
 }
 
 impl JobServer {
     pub fn init(database: Arc<Database>, poll_interval: u64) -> Arc<JobServer> {
         let job_server = Arc::new(JobServer {
             database: database,
+            events: JobEventBus::new(),
+            poll_interval: poll_interval,
+            health: SyncMutex::new(JobServerHealth::Running),
             processing_permit: Semaphore::new(1),
+            new_job_added: Notify::new(),
+            shutdown_stage: watch::Sender::new(ShutdownStage::Running),
+            active_driver: Mutex::new(None),
         });
 
-        JobServer::start_polling(job_server.clone(), poll_interval);
+        JobServer::start_polling(job_server.clone());
 
         job_server
     }
 
-    fn start_polling(job_server: Arc<JobServer>, poll_interval: u64) {
+    fn start_polling(job_server: Arc<JobServer>) {
         tokio::spawn(async move {
+            let mut shutdown = job_server.shutdown_stage.subscribe();
+            let mut consecutive_failures: u32 = 0;
             loop {
-                match job_server.process_all().await {
+                let wait_seconds = match job_server.process_all().await {
                     Ok(_job_count) => {
-                        sleep_seconds(poll_interval).await;
+                        if consecutive_failures > 0 {
+                            log::info!(
+                                "Job server recovered after {} failures",
+                                consecutive_failures
+                            );
+                            consecutive_failures = 0;
+                        }
+                        job_server.set_health(JobServerHealth::Running);
+
+                        job_server.poll_interval
+                    }
+                    Err(error) if is_transient_error(&error) => {
+                        consecutive_failures += 1;
+                        let backoff = get_backoff_seconds(consecutive_failures);
+                        log::warn!("Job server failed, retrying in {}s: {:?}", backoff, error);
+                        job_server.set_health(JobServerHealth::Degraded {
+                            reason: format!("{}", error),
+                            consecutive_failures: consecutive_failures,
+                        });
+
+                        backoff
                     }
                     Err(error) => {
-                        log::error!("{:?}", error);
-                        std::process::exit(1);
+                        log::error!("Job server stopped: {:?}", error);
+                        job_server.set_health(JobServerHealth::Stopped {
+                            reason: format!("{}", error),
+                        });
+
+                        return;
                     }
                 };
+
+                // polling is only a fallback for jobs added outside of this process,
+                // e.g. directly in the database. New jobs should not cut backoff short.
+                select! {
+                    _ = sleep_seconds(wait_seconds) => {},
+                    _ = job_server.new_job_added.notified(), if consecutive_failures == 0 => {},
+                    _ = shutdown.wait_for(|stage| *stage != ShutdownStage::Running) => {
+                        log::info!("Job server stopped polling for new jobs");
+                        job_server.set_health(JobServerHealth::Stopped {
+                            reason: String::from("Server is shutting down"),
+                        });
+
+                        return;
+                    },
+                };
             }
         });
     }
 
+    pub fn get_health(&self) -> JobServerHealth {
+        self.health.lock().unwrap().clone()
+    }
+
+    fn set_health(&self, health: JobServerHealth) {
+        *self.health.lock().unwrap() = health;
+    }
+
+    /* Starts polling loop again after it exited on fatal error. Returns error if
+    the loop is still running, so that two loops don't end up competing for jobs */
+    pub fn restart(job_server: Arc<JobServer>) -> anyhow::Result<()> {
+        if job_server.is_shutting_down() {
+            return Err(anyhow::anyhow!("Server is shutting down"));
+        }
+
+        {
+            let mut health = job_server.health.lock().unwrap();
+            match *health {
+                JobServerHealth::Stopped { .. } => *health = JobServerHealth::Running,
+                _ => return Err(anyhow::anyhow!("Job server is already running")),
+            };
+        }
+
+        log::info!("Restarting job server");
+        JobServer::start_polling(job_server);
+
+        Ok(())
+    }
+
+    /* All jobs should be added through here rather than with Job::add directly, so
+    that polling loop wakes up immediately, and listeners of job events get notified
+    about the new job */
+    pub async fn enqueue(
+        &self,
+        job_params: JobParams,
+        priority: JobPriority,
+        username: Option<&str>,
+    ) -> anyhow::Result<i32> {
+        let job_id = Job::add(&self.database, job_params, priority, username).await?;
+
+        // if the loop is busy processing, the permit is stored and the next wait
+        // returns right away, so jobs added mid-processing are not missed either
+        self.new_job_added.notify_one();
+
+        self.events.publish(JobEvent::StatusChanged {
+            job_id: job_id,
+            status: String::from("QUEUED"),
+        });
+
+        Ok(job_id)
+    }
+
     pub async fn process_all(&self) -> anyhow::Result<i32> {
         let mut job_count: i32 = 0;
         loop {
@@ -64,6 +204,10 @@ impl JobServer {
     async fn process_one(&self) -> anyhow::Result<Option<()>> {
         let _processing_permit = self.processing_permit.acquire().await.unwrap();
 
+        if self.is_shutting_down() {
+            return Ok(None);
+        }
+
         let mut job = match Job::fetch_next(&self.database).await? {
             Some(job) => job,
             None => return Ok(None),
@@ -76,8 +220,22 @@ impl JobServer {
         );
 
         job.mark_as_processing(&self.database).await?;
+        self.publish_status(&job);
 
-        match processor::process(&self.database, &job).await {
+        let mut shutdown_stage = self.shutdown_stage.subscribe();
+        let result = select! {
+            result = processor::process(self, &job) => result,
+            _ = shutdown_stage.wait_for(|stage| *stage == ShutdownStage::Aborting) => {
+                log::info!("Aborting job {} due to shutdown, putting it back in queue", &job.id);
+                job.mark_as_queued(&self.database).await?;
+                self.publish_status(&job);
+                self.quit_active_driver().await;
+
+                return Ok(None);
+            },
+        };
+
+        match result {
             Ok(_) => job.mark_as_successful(&self.database).await?,
             Err(error) => {
                 let message = format!("{}", error);
@@ -85,6 +243,7 @@ impl JobServer {
                 job.mark_as_failed(&self.database, message).await?
             }
         }
+        self.publish_status(&job);
 
         log::debug!(
             "Finished processing of job {} with status :{}",
@@ -94,4 +253,106 @@ impl JobServer {
 
         Ok(Some(()))
     }
+
+    fn publish_status(&self, job: &Job) {
+        self.events.publish(JobEvent::StatusChanged {
+            job_id: job.id,
+            status: job.status.to_string(),
+        });
+    }
+
+    /* Stops picking up new jobs and gives the job in flight grace_period to finish.
+    If it doesn't make it in time, it gets put back in the queue, so that it is
+    picked up again on next start. Either way, the browser session is closed, so
+    geckodriver is not left with orphaned Firefox windows */
+    pub async fn shutdown(&self, grace_period: Duration) {
+        self.shutdown_stage.send_replace(ShutdownStage::Draining);
+
+        if timeout(grace_period, self.processing_permit.acquire())
+            .await
+            .is_err()
+        {
+            log::warn!("Job still running after {:?}, aborting it", grace_period);
+            self.shutdown_stage.send_replace(ShutdownStage::Aborting);
+            let _ = self.processing_permit.acquire().await;
+        }
+
+        self.quit_active_driver().await;
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        *self.shutdown_stage.borrow() != ShutdownStage::Running
+    }
+
+    /* Processor registers each browser session here for the time it's in use, so
+    that it can be closed even if processing of the job gets dropped midway */
+    pub async fn set_active_driver(&self, driver: Option<&WebDriver>) {
+        *self.active_driver.lock().await = driver.cloned();
+    }
+
+    async fn quit_active_driver(&self) {
+        let driver = match self.active_driver.lock().await.take() {
+            Some(driver) => driver,
+            None => return,
+        };
+
+        if let Err(error) = driver.quit().await {
+            log::error!("Could not close browser session: {:?}", error);
+        }
+    }
+}
+
+/* Errors coming from database being temporarily unavailable (locked by other writer,
+pool exhausted, etc.) are worth retrying. Everything else likely means there is
+something wrong with the data or schema, and retrying will not help */
+fn is_transient_error(error: &anyhow::Error) -> bool {
+    let sqlx_error = match error.downcast_ref::<sqlx::Error>() {
+        Some(value) => value,
+        None => return false,
+    };
+
+    match sqlx_error {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed => true,
+        sqlx::Error::Database(db_error) => match db_error.code() {
+            // SQLITE_BUSY and SQLITE_LOCKED, including their extended codes
+            Some(code) => {
+                let primary_code = code.parse::<i32>().unwrap_or(0) & 0xff;
+                primary_code == 5 || primary_code == 6
+            }
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+fn get_backoff_seconds(consecutive_failures: u32) -> u64 {
+    let exponent = consecutive_failures.saturating_sub(1).min(16);
+    let backoff = BACKOFF_BASE_SECONDS.saturating_mul(1 << exponent);
+
+    backoff.min(BACKOFF_MAX_SECONDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_transient_error() {
+        assert!(is_transient_error(&anyhow::anyhow!(
+            sqlx::Error::PoolTimedOut
+        )));
+
+        assert!(!is_transient_error(&anyhow::anyhow!(
+            sqlx::Error::RowNotFound
+        )));
+        assert!(!is_transient_error(&anyhow::anyhow!("Some other error")));
+    }
+
+    #[test]
+    fn test_get_backoff_seconds() {
+        assert_eq!(get_backoff_seconds(1), BACKOFF_BASE_SECONDS);
+        assert_eq!(get_backoff_seconds(2), 2 * BACKOFF_BASE_SECONDS);
+        assert_eq!(get_backoff_seconds(3), 4 * BACKOFF_BASE_SECONDS);
+        assert_eq!(get_backoff_seconds(1000), BACKOFF_MAX_SECONDS);
+    }
 }
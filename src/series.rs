@@ -1,6 +1,7 @@
 use serde::Serialize;
 use ts_rs::TS;
 
+use crate::books::Book;
 use crate::common::TS_FILE;
 use crate::database::Database;
 use crate::user::User;
@@ -24,6 +25,8 @@ pub struct BookSeriesWithStatus {
     pub count: i32,
     pub subscribed: bool,
     pub subscribers: i32,
+    // of the books user rated themselves, None if there are none
+    pub average_rating: Option<f64>,
 }
 
 #[derive(Serialize, TS, Debug)]
@@ -32,6 +35,30 @@ pub struct GetAllSeriesResult {
     pub series: Vec<BookSeriesWithStatus>,
 }
 
+/* Series without anything related to users, safe to show without login */
+#[derive(sqlx::FromRow, Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct PublicBookSeries {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    #[ts(flatten)]
+    pub series: BookSeries,
+    pub count: i32,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetAllPublicSeriesResult {
+    pub series: Vec<PublicBookSeries>,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetSeriesDetailResult {
+    pub series: PublicBookSeries,
+    pub books: Vec<Book>,
+}
+
 #[derive(Serialize, TS, Debug)]
 #[ts(export_to = TS_FILE)]
 pub struct AddSeriesResult {
@@ -100,7 +127,8 @@ impl BookSeries {
             series.*,
             MAX(IIF(subscriptions.username = ?1, 1, 0)) as subscribed,
             SUM(IIF(subscriptions.username IS NOT NULL, 1, 0)) as subscribers,
-            IIF(books.count IS NOT NULL, books.count, 0) as count
+            IIF(books.count IS NOT NULL, books.count, 0) as count,
+            ratings.average_rating
           FROM series
           LEFT JOIN subscriptions
             ON (series.asin = subscriptions.series_asin)
@@ -110,6 +138,13 @@ impl BookSeries {
             LEFT JOIN books ON (series.asin = books.series_asin)
             GROUP BY series.asin
           ) books USING (asin)
+          LEFT JOIN (
+            SELECT books.series_asin AS asin, AVG(book_ratings.rating) AS average_rating
+            FROM book_ratings
+            JOIN books ON (books.asin = book_ratings.book_asin)
+            WHERE book_ratings.username = ?1 AND book_ratings.rating IS NOT NULL
+            GROUP BY books.series_asin
+          ) ratings USING (asin)
           GROUP BY 1, 2, 3
           ORDER BY series.name",
         )
@@ -122,14 +157,56 @@ impl BookSeries {
         })
     }
 
-    pub async fn delete_by_asin(db: &Database, asin: &str) -> anyhow::Result<()> {
+    /* Series asin is optional, without it all series are returned */
+    async fn fetch_public(
+        db: &Database,
+        asin: Option<&str>,
+    ) -> anyhow::Result<Vec<PublicBookSeries>> {
         let mut conn = db.acquire_db_conn().await?;
+        let series_list = sqlx::query_as::<_, PublicBookSeries>(
+            "SELECT series.*, COUNT(books.asin) AS count
+            FROM series
+            LEFT JOIN books ON (series.asin = books.series_asin)
+            WHERE ?1 IS NULL OR series.asin = ?1
+            GROUP BY series.asin
+            ORDER BY series.name",
+        )
+        .bind(asin)
+        .fetch_all(&mut *conn)
+        .await?;
 
-        // Yea, I know it's not really clean to do it from this class, and I'm not feeling
-        // creative enough to restructure this again :D
-        sqlx::query!("DELETE FROM books WHERE series_asin = ?1", asin)
-            .execute(&mut *conn)
-            .await?;
+        Ok(series_list)
+    }
+
+    pub async fn fetch_all_public(db: &Database) -> anyhow::Result<GetAllPublicSeriesResult> {
+        let series_list = BookSeries::fetch_public(db, None).await?;
+
+        Ok(GetAllPublicSeriesResult {
+            series: series_list,
+        })
+    }
+
+    pub async fn fetch_detail(
+        db: &Database,
+        asin: &str,
+    ) -> anyhow::Result<Option<GetSeriesDetailResult>> {
+        let series = match BookSeries::fetch_public(db, Some(asin)).await?.pop() {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let mut books = Book::fetch_by_series_asin(db, asin).await?;
+        books.sort_by_key(|book| book.ordinal);
+
+        Ok(Some(GetSeriesDetailResult {
+            series: series,
+            books: books,
+        }))
+    }
+
+    /* Books, and everything users had on them or the series, go with it */
+    pub async fn delete_by_asin(db: &Database, asin: &str) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
 
         sqlx::query!("DELETE FROM series WHERE asin = ?1", asin)
             .execute(&mut *conn)
@@ -0,0 +1,220 @@
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::common::{now, TS_FILE};
+use crate::crypto::{generate_token, hash_token};
+use crate::database::Database;
+
+pub const SESSION_COOKIE: &str = "session";
+
+// last seen time is only needed with minute precision, no need to write on every request
+const LAST_SEEN_RESOLUTION_MS: i64 = 60 * 1000;
+
+/* Both timeouts are in milliseconds, to be directly comparable with timestamps */
+pub struct SessionSettings {
+    pub idle_timeout: i64,
+    pub max_age: i64,
+}
+
+impl SessionSettings {
+    pub fn new(idle_timeout_hours: u64, max_age_days: u64) -> SessionSettings {
+        let hour: i64 = 60 * 60 * 1000;
+
+        SessionSettings {
+            idle_timeout: (idle_timeout_hours as i64) * hour,
+            max_age: (max_age_days as i64) * 24 * hour,
+        }
+    }
+
+    pub fn is_expired(&self, session: &Session, time_now: i64) -> bool {
+        time_now - session.time_last_seen > self.idle_timeout
+            || time_now - session.time_created > self.max_age
+    }
+}
+
+#[derive(sqlx::FromRow, Serialize, TS, Debug)]
+pub struct Session {
+    pub id: i32,
+    #[serde(skip)]
+    #[ts(skip)]
+    pub username: String,
+    pub user_agent: Option<String>,
+    #[ts(as = "i32")]
+    pub time_created: i64,
+    #[ts(as = "i32")]
+    pub time_last_seen: i64,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE, rename = "Session")]
+pub struct SessionWithStatus {
+    #[serde(flatten)]
+    #[ts(flatten)]
+    pub session: Session,
+    pub current: bool,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetAllSessionsResult {
+    pub sessions: Vec<SessionWithStatus>,
+}
+
+impl Session {
+    /* Returns secret that identifies the session. It should only be stored in the
+    private cookie, database only keeps its hash */
+    pub async fn create(
+        db: &Database,
+        username: &str,
+        user_agent: Option<&str>,
+    ) -> anyhow::Result<String> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let secret = generate_token();
+        let secret_hash = hash_token(&secret)?;
+        let time_now = now();
+        sqlx::query!(
+            "INSERT INTO sessions
+            (username, secret_hash, user_agent, time_created, time_last_seen)
+            VALUES (?1, ?2, ?3, ?4, ?4)",
+            username,
+            secret_hash,
+            user_agent,
+            time_now,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(secret)
+    }
+
+    pub async fn fetch_by_secret(db: &Database, secret: &str) -> anyhow::Result<Option<Session>> {
+        let secret_hash = hash_token(secret)?;
+
+        let mut conn = db.acquire_db_conn().await?;
+        let session = sqlx::query_as::<_, Session>(
+            "SELECT id, username, user_agent, time_created, time_last_seen
+            FROM sessions WHERE secret_hash = ?1",
+        )
+        .bind(secret_hash)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(session)
+    }
+
+    pub async fn fetch_by_username(db: &Database, username: &str) -> anyhow::Result<Vec<Session>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let sessions = sqlx::query_as::<_, Session>(
+            "SELECT id, username, user_agent, time_created, time_last_seen
+            FROM sessions WHERE username = ?1 ORDER BY time_last_seen DESC",
+        )
+        .bind(username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    pub async fn mark_as_seen(&mut self, db: &Database) -> anyhow::Result<()> {
+        let time_now = now();
+        if time_now - self.time_last_seen < LAST_SEEN_RESOLUTION_MS {
+            return Ok(());
+        }
+
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "UPDATE sessions SET time_last_seen = ?1 WHERE id = ?2",
+            time_now,
+            self.id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        self.time_last_seen = time_now;
+
+        Ok(())
+    }
+
+    pub async fn delete(&self, db: &Database) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!("DELETE FROM sessions WHERE id = ?1", self.id)
+            .execute(&mut *conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_by_id(db: &Database, username: &str, id: i32) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE username = ?1 AND id = ?2",
+            username,
+            id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /* Logs the user out everywhere, except for the session passed in keep_id */
+    pub async fn delete_by_username(
+        db: &Database,
+        username: &str,
+        keep_id: Option<i32>,
+    ) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "DELETE FROM sessions WHERE username = ?1 AND id IS NOT ?2",
+            username,
+            keep_id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(db: &Database, settings: &SessionSettings) -> anyhow::Result<()> {
+        let mut conn = db.acquire_db_conn().await?;
+        let time_now = now();
+        let last_seen_cutoff = time_now - settings.idle_timeout;
+        let created_cutoff = time_now - settings.max_age;
+        sqlx::query!(
+            "DELETE FROM sessions WHERE time_last_seen < ?1 OR time_created < ?2",
+            last_seen_cutoff,
+            created_cutoff,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(time_created: i64, time_last_seen: i64) -> Session {
+        Session {
+            id: 1,
+            username: String::from("user"),
+            user_agent: None,
+            time_created: time_created,
+            time_last_seen: time_last_seen,
+        }
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let hour: i64 = 60 * 60 * 1000;
+        let settings = SessionSettings::new(2, 1);
+
+        assert!(!settings.is_expired(&session(0, 0), hour));
+        assert!(settings.is_expired(&session(0, 0), 3 * hour));
+        assert!(!settings.is_expired(&session(0, 23 * hour), 24 * hour));
+        assert!(settings.is_expired(&session(0, 24 * hour), 25 * hour));
+    }
+}
@@ -0,0 +1,272 @@
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use ts_rs::TS;
+
+use crate::common::{today, TS_FILE};
+use crate::database::Database;
+use crate::user::User;
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct PeriodCount {
+    // YYYY for years, YYYY-MM for months
+    pub period: String,
+    pub count: u32,
+}
+
+#[derive(sqlx::FromRow, Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct SeriesCompletion {
+    pub series_asin: String,
+    pub name: String,
+    pub released: u32,
+    pub read: u32,
+    // None if nothing has been released yet
+    pub completion_percent: Option<f64>,
+}
+
+/* Only finished readings count, re-reads included. Books without release date are
+the ones that came out before they were first scraped, so they count as released */
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetStatsResult {
+    pub read_by_year: Vec<PeriodCount>,
+    pub read_by_month: Vec<PeriodCount>,
+    pub series_completion: Vec<SeriesCompletion>,
+    // between release and first finish, of books with known release date
+    pub average_days_to_read: Option<f64>,
+    // released, but never read books in subscribed series
+    pub backlog: u32,
+    // consecutive months with at least one finished book
+    pub current_streak_months: u32,
+    pub longest_streak_months: u32,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+struct FinishedReading {
+    book_asin: String,
+    finish_date: String,
+    release_date: Option<String>,
+}
+
+pub async fn fetch_by_user(db: &Database, user: &User) -> anyhow::Result<GetStatsResult> {
+    let mut conn = db.acquire_db_conn().await?;
+
+    let finished = sqlx::query_as::<_, FinishedReading>(
+        "SELECT readings.book_asin, readings.finish_date, books.release_date
+        FROM readings
+        LEFT JOIN books ON (books.asin = readings.book_asin)
+        WHERE readings.username = ?1
+          AND readings.status = 'read'
+          AND readings.finish_date IS NOT NULL
+        ORDER BY readings.finish_date",
+    )
+    .bind(&user.username)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let series_completion = sqlx::query_as::<_, SeriesCompletion>(
+        "SELECT
+          series.asin AS series_asin,
+          series.name,
+          COUNT(books.asin) AS released,
+          COUNT(read_books.book_asin) AS read,
+          IIF(
+            COUNT(books.asin) > 0,
+            100.0 * COUNT(read_books.book_asin) / COUNT(books.asin),
+            NULL
+          ) AS completion_percent
+        FROM subscriptions
+        JOIN series ON (series.asin = subscriptions.series_asin)
+        LEFT JOIN books
+          ON (books.series_asin = series.asin
+            AND (books.release_date IS NULL OR books.release_date <= ?2))
+        LEFT JOIN (
+          SELECT DISTINCT book_asin FROM readings WHERE username = ?1 AND status = 'read'
+        ) read_books ON (read_books.book_asin = books.asin)
+        WHERE subscriptions.username = ?1
+        GROUP BY series.asin
+        ORDER BY series.name",
+    )
+    .bind(&user.username)
+    .bind(today())
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let finish_dates: Vec<&str> = finished
+        .iter()
+        .map(|reading| reading.finish_date.as_str())
+        .collect();
+    let (current_streak_months, longest_streak_months) = get_month_streaks(&finish_dates, &today());
+
+    Ok(GetStatsResult {
+        read_by_year: count_by_period(&finish_dates, 4),
+        read_by_month: count_by_period(&finish_dates, 7),
+        backlog: series_completion
+            .iter()
+            .map(|series| series.released - series.read)
+            .sum(),
+        series_completion: series_completion,
+        average_days_to_read: get_average_days_to_read(&finished),
+        current_streak_months: current_streak_months,
+        longest_streak_months: longest_streak_months,
+    })
+}
+
+/* Dates are YYYY-MM-DD, so the period is just the prefix of given length */
+fn count_by_period(dates: &[&str], prefix_length: usize) -> Vec<PeriodCount> {
+    let mut counts: BTreeMap<&str, u32> = BTreeMap::new();
+    for date in dates.iter().filter(|date| date.len() >= prefix_length) {
+        *counts.entry(&date[..prefix_length]).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|(period, count)| PeriodCount {
+            period: period.to_string(),
+            count: count,
+        })
+        .collect()
+}
+
+fn get_average_days_to_read(finished: &[FinishedReading]) -> Option<f64> {
+    // re-reads would skew it, only the first finish of each book matters. Readings
+    // are ordered by finish date, so that's the first one seen
+    let mut first_finish: HashMap<&str, (&str, &str)> = HashMap::new();
+    for reading in finished {
+        if let Some(release_date) = &reading.release_date {
+            first_finish
+                .entry(reading.book_asin.as_str())
+                .or_insert((reading.finish_date.as_str(), release_date.as_str()));
+        }
+    }
+
+    let days: Vec<i64> = first_finish
+        .values()
+        .filter_map(|(finish_date, release_date)| days_between(release_date, finish_date))
+        .collect();
+
+    if days.is_empty() {
+        return None;
+    }
+    Some(days.iter().sum::<i64>() as f64 / days.len() as f64)
+}
+
+fn days_between(from: &str, to: &str) -> Option<i64> {
+    let from = NaiveDate::parse_from_str(from, "%Y-%m-%d").ok()?;
+    let to = NaiveDate::parse_from_str(to, "%Y-%m-%d").ok()?;
+    Some((to - from).num_days())
+}
+
+fn get_month_index(date: &str) -> Option<i32> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(date.year() * 12 + date.month0() as i32)
+}
+
+/* Returns (current, longest). Current streak isn't broken until the current month is
+over, so it may end either this month or the previous one */
+fn get_month_streaks(dates: &[&str], today: &str) -> (u32, u32) {
+    let mut months: Vec<i32> = dates
+        .iter()
+        .filter_map(|date| get_month_index(date))
+        .collect();
+    months.sort();
+    months.dedup();
+
+    let mut longest = 0;
+    let mut streak = 0;
+    let mut previous: Option<i32> = None;
+    for month in months.iter() {
+        streak = match previous {
+            Some(previous) if previous + 1 == *month => streak + 1,
+            _ => 1,
+        };
+        longest = longest.max(streak);
+        previous = Some(*month);
+    }
+
+    let current = match (previous, get_month_index(today)) {
+        (Some(last), Some(this_month)) if this_month - last <= 1 => streak,
+        _ => 0,
+    };
+
+    (current, longest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_by_period() {
+        let dates = ["2024-12-30", "2025-01-02", "2025-01-15", "2025-03-01"];
+        let count = |prefix_length| -> Vec<(String, u32)> {
+            count_by_period(&dates, prefix_length)
+                .into_iter()
+                .map(|count| (count.period, count.count))
+                .collect()
+        };
+
+        assert_eq!(
+            count(4),
+            vec![(String::from("2024"), 1), (String::from("2025"), 3)]
+        );
+        assert_eq!(
+            count(7),
+            vec![
+                (String::from("2024-12"), 1),
+                (String::from("2025-01"), 2),
+                (String::from("2025-03"), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_days_between() {
+        assert_eq!(days_between("2024-12-30", "2025-01-02"), Some(3));
+        assert_eq!(days_between("2025-01-02", "2024-12-30"), Some(-3));
+        assert_eq!(days_between("2025-02-31", "2025-03-01"), None);
+    }
+
+    #[test]
+    fn test_get_average_days_to_read() {
+        let reading = |asin: &str, finish_date: &str, release_date: Option<&str>| FinishedReading {
+            book_asin: asin.to_string(),
+            finish_date: finish_date.to_string(),
+            release_date: release_date.map(String::from),
+        };
+
+        assert_eq!(get_average_days_to_read(&[]), None);
+        assert_eq!(
+            get_average_days_to_read(&[reading("A", "2025-01-05", None)]),
+            None
+        );
+
+        // re-read of A is ignored
+        let finished = [
+            reading("A", "2025-01-11", Some("2025-01-01")),
+            reading("A", "2025-06-01", Some("2025-01-01")),
+            reading("B", "2025-01-21", Some("2025-01-01")),
+        ];
+        assert_eq!(get_average_days_to_read(&finished), Some(15.0));
+    }
+
+    #[test]
+    fn test_get_month_streaks() {
+        assert_eq!(get_month_streaks(&[], "2025-03-10"), (0, 0));
+
+        let dates = [
+            "2024-11-02",
+            "2024-12-30",
+            "2025-01-02",
+            "2025-01-15",
+            "2025-05-01",
+            "2025-06-20",
+        ];
+        assert_eq!(get_month_streaks(&dates, "2025-06-21"), (2, 3));
+        // nothing read yet this month, but the streak still holds
+        assert_eq!(get_month_streaks(&dates, "2025-07-01"), (2, 3));
+        assert_eq!(get_month_streaks(&dates, "2025-08-01"), (0, 3));
+    }
+}
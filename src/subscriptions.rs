@@ -1,4 +1,5 @@
 use serde::Serialize;
+use sqlx::sqlite::SqliteConnection;
 
 use crate::database::Database;
 use crate::user::User;
@@ -12,6 +13,14 @@ pub struct Subscription {
 impl Subscription {
     pub async fn add(db: &Database, user: &User, series_asin: &str) -> anyhow::Result<()> {
         let mut conn = db.acquire_db_conn().await?;
+        Subscription::add_with_conn(&mut conn, user, series_asin).await
+    }
+
+    pub async fn add_with_conn(
+        conn: &mut SqliteConnection,
+        user: &User,
+        series_asin: &str,
+    ) -> anyhow::Result<()> {
         sqlx::query!(
             "INSERT INTO subscriptions (username, series_asin) VALUES(?1, ?2)",
             user.username,
@@ -35,4 +44,16 @@ impl Subscription {
 
         Ok(())
     }
+
+    pub async fn fetch_series_asins(db: &Database, user: &User) -> anyhow::Result<Vec<String>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let series_asins = sqlx::query_scalar::<_, String>(
+            "SELECT series_asin FROM subscriptions WHERE username = ?1 ORDER BY series_asin",
+        )
+        .bind(&user.username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(series_asins)
+    }
 }
@@ -0,0 +1,139 @@
+use serde::Serialize;
+use ts_rs::TS;
+
+use crate::common::{now, TS_FILE};
+use crate::crypto::{generate_token, hash_token};
+use crate::database::Database;
+
+// makes tokens easy to recognize, e.g. when they get pasted somewhere by accident
+const TOKEN_PREFIX: &str = "bst_";
+// scripts poll often, last use is only shown with minute precision anyway
+const LAST_USED_RESOLUTION_MS: i64 = 60 * 1000;
+
+#[derive(sqlx::FromRow, Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct ApiToken {
+    pub id: i32,
+    #[serde(skip)]
+    #[ts(skip)]
+    pub username: String,
+    pub name: String,
+    pub read_only: bool,
+    #[ts(as = "i32")]
+    pub time_created: i64,
+    #[ts(as = "Option<i32>")]
+    pub time_last_used: Option<i64>,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct GetAllTokensResult {
+    pub tokens: Vec<ApiToken>,
+}
+
+/* Secret is only ever returned once, right after creation */
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct CreateTokenResult {
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+impl ApiToken {
+    pub async fn create(
+        db: &Database,
+        username: &str,
+        name: &str,
+        read_only: bool,
+    ) -> anyhow::Result<CreateTokenResult> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let secret = format!("{}{}", TOKEN_PREFIX, generate_token());
+        let token_hash = hash_token(&secret)?;
+        let time_created = now();
+
+        let token = sqlx::query_as::<_, ApiToken>(
+            "INSERT INTO api_tokens (username, name, token_hash, read_only, time_created)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            RETURNING id, username, name, read_only, time_created, time_last_used",
+        )
+        .bind(username)
+        .bind(name)
+        .bind(token_hash)
+        .bind(read_only)
+        .bind(time_created)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(CreateTokenResult {
+            token: token,
+            secret: secret,
+        })
+    }
+
+    pub async fn fetch_by_username(
+        db: &Database,
+        username: &str,
+    ) -> anyhow::Result<GetAllTokensResult> {
+        let mut conn = db.acquire_db_conn().await?;
+        let tokens = sqlx::query_as::<_, ApiToken>(
+            "SELECT id, username, name, read_only, time_created, time_last_used
+            FROM api_tokens WHERE username = ?1 ORDER BY name",
+        )
+        .bind(username)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(GetAllTokensResult { tokens: tokens })
+    }
+
+    pub async fn fetch_by_secret(db: &Database, secret: &str) -> anyhow::Result<Option<ApiToken>> {
+        let token_hash = hash_token(secret)?;
+
+        let mut conn = db.acquire_db_conn().await?;
+        let token = sqlx::query_as::<_, ApiToken>(
+            "SELECT id, username, name, read_only, time_created, time_last_used
+            FROM api_tokens WHERE token_hash = ?1",
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn mark_as_used(&self, db: &Database) -> anyhow::Result<()> {
+        let time_last_used = now();
+        if self
+            .time_last_used
+            .is_some_and(|time| time_last_used - time < LAST_USED_RESOLUTION_MS)
+        {
+            return Ok(());
+        }
+
+        let mut conn = db.acquire_db_conn().await?;
+        sqlx::query!(
+            "UPDATE api_tokens SET time_last_used = ?1 WHERE id = ?2",
+            time_last_used,
+            self.id,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /* Returns false if there was no such token, so callers can report it */
+    pub async fn delete_by_name(db: &Database, username: &str, name: &str) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!(
+            "DELETE FROM api_tokens WHERE username = ?1 AND name = ?2",
+            username,
+            name,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
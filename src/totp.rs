@@ -0,0 +1,419 @@
+use serde::Serialize;
+use sqlx::Connection;
+use std::env;
+use totp_lite::{totp_custom, Sha1};
+use ts_rs::TS;
+
+use crate::common::{now, TS_FILE};
+use crate::crypto::{
+    decrypt, encrypt, generate_token, hash_token, parse_key, random_bytes, SECRETBOX_KEYBYTES,
+};
+use crate::database::Database;
+
+const TOTP_KEY_ENV_VAR: &str = "TOTP_ENCRYPTION_KEY";
+const TOTP_ISSUER: &str = "BST";
+const TOTP_STEP_SECONDS: u64 = 30;
+const TOTP_DIGITS: u32 = 6;
+// 160 bits, as recommended by RFC 4226 for HMAC-SHA1
+const TOTP_SECRET_BYTES: usize = 20;
+// codes from neighbouring steps are accepted too, to tolerate clock drift of the phone
+const TOTP_ALLOWED_DRIFT_STEPS: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+/* Key used to encrypt TOTP secrets at rest. 2FA can't be enabled without it, and
+users who already have it enabled can't log in if it goes missing or changes */
+pub struct TotpSettings {
+    key: Option<[u8; SECRETBOX_KEYBYTES]>,
+}
+
+impl TotpSettings {
+    pub fn from_env() -> anyhow::Result<TotpSettings> {
+        let key = match env::var(TOTP_KEY_ENV_VAR) {
+            Ok(value) => match parse_key(&value) {
+                Ok(key) => Some(key),
+                Err(_) => {
+                    return Err(anyhow::anyhow!(
+                        "{} must be {} bytes encoded as hex",
+                        TOTP_KEY_ENV_VAR,
+                        SECRETBOX_KEYBYTES
+                    ))
+                }
+            },
+            Err(_) => {
+                log::info!("{} env variable unset, 2FA is disabled.", TOTP_KEY_ENV_VAR);
+                None
+            }
+        };
+
+        Ok(TotpSettings { key: key })
+    }
+
+    pub fn get_key(&self) -> anyhow::Result<&[u8; SECRETBOX_KEYBYTES]> {
+        match &self.key {
+            Some(key) => Ok(key),
+            None => Err(anyhow::anyhow!("2FA is not configured on this server")),
+        }
+    }
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct TotpStatus {
+    pub enabled: bool,
+    pub recovery_codes_left: u32,
+}
+
+/* Returned when enrollment starts. Secret is shown once, for apps that can't scan
+the provisioning URI as QR code */
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct RecoveryCodesResult {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct Totp {
+    pub username: String,
+    secret_encrypted: String,
+    // set once the user proves their app generates valid codes
+    pub confirmed: bool,
+    // steps can't be reused, so that code seen by someone else is worthless
+    last_used_step: Option<i64>,
+}
+
+impl Totp {
+    pub async fn fetch_by_username(db: &Database, username: &str) -> anyhow::Result<Option<Totp>> {
+        let mut conn = db.acquire_db_conn().await?;
+        let totp = sqlx::query_as::<_, Totp>(
+            "SELECT username, secret_encrypted, confirmed, last_used_step
+            FROM totp WHERE username = ?1",
+        )
+        .bind(username)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(totp)
+    }
+
+    /* Replaces any previous enrollment that was not confirmed. Callers have to make
+    sure confirmed one is not overwritten, as that would be a way around 2FA */
+    pub async fn begin_enrollment(
+        db: &Database,
+        settings: &TotpSettings,
+        username: &str,
+    ) -> anyhow::Result<TotpEnrollment> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let secret = random_bytes::<TOTP_SECRET_BYTES>();
+        let secret_encrypted = match encrypt(settings.get_key()?, &secret) {
+            Ok(value) => value,
+            Err(_) => return Err(anyhow::anyhow!("Encrypting 2FA secret failed")),
+        };
+
+        let time_created = now();
+        sqlx::query!(
+            "INSERT OR REPLACE INTO totp (username, secret_encrypted, confirmed, time_created)
+            VALUES (?1, ?2, 0, ?3)",
+            username,
+            secret_encrypted,
+            time_created,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        let secret = base32_encode(&secret);
+        Ok(TotpEnrollment {
+            provisioning_uri: get_provisioning_uri(&secret, username),
+            secret: secret,
+        })
+    }
+
+    /* Checks the code against the secret, and uses up its step if valid. Confirms
+    the enrollment on first valid code */
+    pub async fn verify(
+        &mut self,
+        db: &Database,
+        settings: &TotpSettings,
+        code: &str,
+    ) -> anyhow::Result<bool> {
+        let secret = match decrypt(settings.get_key()?, &self.secret_encrypted) {
+            Ok(value) => value,
+            Err(_) => return Err(anyhow::anyhow!("Decrypting 2FA secret failed")),
+        };
+
+        let step = match find_matching_step(&secret, code.trim(), now()) {
+            Some(value) => value as i64,
+            None => return Ok(false),
+        };
+        if self
+            .last_used_step
+            .is_some_and(|last_step| step <= last_step)
+        {
+            return Ok(false);
+        }
+
+        // checked again in the same statement, so that parallel logins can't both use it
+        let mut conn = db.acquire_db_conn().await?;
+        let result = sqlx::query!(
+            "UPDATE totp SET confirmed = 1, last_used_step = ?1
+            WHERE username = ?2 AND (last_used_step IS NULL OR last_used_step < ?1)",
+            step,
+            self.username,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        if result.rows_affected() != 1 {
+            return Ok(false);
+        }
+
+        self.confirmed = true;
+        self.last_used_step = Some(step);
+
+        Ok(true)
+    }
+
+    /* Turns 2FA off, together with any recovery codes. Returns false if the user
+    didn't have it set up */
+    pub async fn delete_by_username(db: &Database, username: &str) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        let result = sqlx::query!("DELETE FROM totp WHERE username = ?1", username)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            "DELETE FROM totp_recovery_codes WHERE username = ?1",
+            username
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_status(db: &Database, username: &str) -> anyhow::Result<TotpStatus> {
+        let enabled = match Totp::fetch_by_username(db, username).await? {
+            Some(totp) => totp.confirmed,
+            None => false,
+        };
+
+        let mut conn = db.acquire_db_conn().await?;
+        let recovery_codes_left = sqlx::query_scalar::<_, u32>(
+            "SELECT COUNT(*) FROM totp_recovery_codes WHERE username = ?1",
+        )
+        .bind(username)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        Ok(TotpStatus {
+            enabled: enabled,
+            recovery_codes_left: recovery_codes_left,
+        })
+    }
+
+    /* Replaces all previous recovery codes. Like secret, codes are only returned
+    here, database keeps their hashes */
+    pub async fn create_recovery_codes(
+        db: &Database,
+        username: &str,
+    ) -> anyhow::Result<RecoveryCodesResult> {
+        let mut conn = db.acquire_db_conn().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM totp_recovery_codes WHERE username = ?1",
+            username
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let mut recovery_codes = Vec::new();
+        for _ in 0..RECOVERY_CODE_COUNT {
+            let code = generate_token()[..RECOVERY_CODE_LENGTH].to_string();
+            let code_hash = hash_recovery_code(&code)?;
+            sqlx::query!(
+                "INSERT INTO totp_recovery_codes (code_hash, username) VALUES (?1, ?2)",
+                code_hash,
+                username,
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            // split in half, so it's easier to copy from paper
+            let (first, second) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+            recovery_codes.push(format!("{}-{}", first, second));
+        }
+
+        tx.commit().await?;
+
+        Ok(RecoveryCodesResult {
+            recovery_codes: recovery_codes,
+        })
+    }
+
+    /* Each recovery code works only once. Returns false if it's not valid */
+    pub async fn use_recovery_code(
+        db: &Database,
+        username: &str,
+        code: &str,
+    ) -> anyhow::Result<bool> {
+        let mut conn = db.acquire_db_conn().await?;
+
+        let code_hash = hash_recovery_code(code)?;
+        let result = sqlx::query!(
+            "DELETE FROM totp_recovery_codes WHERE username = ?1 AND code_hash = ?2",
+            username,
+            code_hash,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn hash_recovery_code(code: &str) -> anyhow::Result<String> {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    hash_token(&normalized)
+}
+
+fn generate_code(secret: &[u8], step: u64) -> String {
+    totp_custom::<Sha1>(
+        TOTP_STEP_SECONDS,
+        TOTP_DIGITS,
+        secret,
+        step * TOTP_STEP_SECONDS,
+    )
+}
+
+fn find_matching_step(secret: &[u8], code: &str, time_now_ms: i64) -> Option<u64> {
+    let current_step = (time_now_ms as u64) / 1000 / TOTP_STEP_SECONDS;
+    let first_step = current_step.saturating_sub(TOTP_ALLOWED_DRIFT_STEPS);
+    let last_step = current_step + TOTP_ALLOWED_DRIFT_STEPS;
+
+    (first_step..=last_step).find(|step| generate_code(secret, *step) == code)
+}
+
+/* RFC 4648 alphabet without padding, which is what authenticator apps expect */
+fn base32_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let mut encoded = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits: u32 = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | (*byte as u32);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+
+    encoded
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/* See https://github.com/google/google-authenticator/wiki/Key-Uri-Format */
+fn get_provisioning_uri(secret: &str, username: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        TOTP_ISSUER,
+        percent_encode(username),
+        secret,
+        TOTP_ISSUER,
+        TOTP_DIGITS,
+        TOTP_STEP_SECONDS
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test vectors from RFC 6238 appendix B, truncated to 6 digits
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_generate_code() {
+        assert_eq!(generate_code(RFC_SECRET, 59 / TOTP_STEP_SECONDS), "287082");
+        assert_eq!(
+            generate_code(RFC_SECRET, 1111111109 / TOTP_STEP_SECONDS),
+            "081804"
+        );
+        assert_eq!(
+            generate_code(RFC_SECRET, 2000000000 / TOTP_STEP_SECONDS),
+            "279037"
+        );
+    }
+
+    #[test]
+    fn test_find_matching_step() {
+        let time_ms = 1111111109 * 1000;
+        let step = 1111111109 / TOTP_STEP_SECONDS;
+        assert_eq!(
+            find_matching_step(RFC_SECRET, "081804", time_ms),
+            Some(step)
+        );
+
+        // code from previous step is still accepted, but not from the one before it
+        let next_step_ms = time_ms + (TOTP_STEP_SECONDS as i64) * 1000;
+        assert_eq!(
+            find_matching_step(RFC_SECRET, "081804", next_step_ms),
+            Some(step)
+        );
+        let step_after_ms = time_ms + 2 * (TOTP_STEP_SECONDS as i64) * 1000;
+        assert_eq!(
+            find_matching_step(RFC_SECRET, "081804", step_after_ms),
+            None
+        );
+
+        assert_eq!(find_matching_step(RFC_SECRET, "000000", time_ms), None);
+    }
+
+    #[test]
+    fn test_base32_encode() {
+        assert_eq!(base32_encode(b""), "");
+        assert_eq!(base32_encode(b"f"), "MY");
+        assert_eq!(base32_encode(b"foob"), "MZXW6YQ");
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI");
+    }
+
+    #[test]
+    fn test_get_provisioning_uri() {
+        assert_eq!(
+            get_provisioning_uri("MZXW6YTBOI", "john doe"),
+            "otpauth://totp/BST:john%20doe?secret=MZXW6YTBOI&issuer=BST&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
@@ -1,22 +1,61 @@
+use rocket::form::FromFormField;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome, Request};
 use rocket::State;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use ts_rs::TS;
 
-use crate::common::TS_FILE;
+use crate::common::{now, TS_FILE};
 use crate::credentials::Credentials;
+use crate::crypto::{generate_token, hash_password};
 use crate::database::Database;
+use crate::proxy_auth::ProxyAuthSettings;
+use crate::sessions::{Session, SessionSettings, SESSION_COOKIE};
+use crate::tokens::ApiToken;
+
+#[derive(
+    sqlx::Type,
+    clap::ValueEnum,
+    FromFormField,
+    Deserialize,
+    Serialize,
+    TS,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+)]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[ts(export_to = TS_FILE)]
+pub enum Role {
+    // manages series, scrapes, and other users' shared data
+    #[field(value = "admin")]
+    Admin,
+    // manages own subscriptions and read state
+    #[field(value = "member")]
+    Member,
+    // can only browse
+    #[field(value = "read_only")]
+    ReadOnly,
+}
 
 /* This struct is used as request guard to ensure logged in user, and on the
-frontend to display username, and is returned by /me and /login api routes. If
-at any point need arises to add more fields here, remove TS trait, and create
-lightweight object with just username to return from login routes instead */
+frontend to display username and hide actions the user has no access to. It is
+returned by /me and /login api routes, so it should never carry anything that is
+not safe to show to the user themselves */
 #[derive(Serialize, TS, Debug)]
 #[ts(export_to = TS_FILE)]
 pub struct User {
     pub username: String,
+    pub role: Role,
+}
+
+impl User {
+    pub fn can_write(&self) -> bool {
+        self.role != Role::ReadOnly
+    }
 }
 
 #[rocket::async_trait]
@@ -26,19 +65,23 @@ impl<'r> FromRequest<'r> for &'r User {
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
         let user = request
             .local_cache_async(async {
-                let username = match request.cookies().get_private("user") {
-                    Some(cookie) => String::from(cookie.value()),
-                    None => return Err(()),
+                let db = match request.guard::<&State<Arc<Database>>>().await {
+                    Outcome::Success(value) => value,
+                    _ => return Err(()),
                 };
 
-                let db = match request.guard::<&State<Arc<Database>>>().await {
+                let proxy_auth = match request.guard::<&State<ProxyAuthSettings>>().await {
                     Outcome::Success(value) => value,
                     _ => return Err(()),
                 };
 
-                match Credentials::fetch_by_username(db, &username).await {
-                    Ok(Some(_)) => Ok(User { username: username }),
-                    _ => Err(()),
+                // scripts and feed readers can't log in, so they send token instead
+                match request.headers().get_one("Authorization") {
+                    Some(header) => get_user_from_token(db, header).await,
+                    None if proxy_auth.is_enabled() => {
+                        get_user_from_proxy(db, proxy_auth, request).await
+                    }
+                    None => get_user_from_cookie(db, request).await,
                 }
             })
             .await;
@@ -49,3 +92,147 @@ impl<'r> FromRequest<'r> for &'r User {
         }
     }
 }
+
+async fn get_user_from_cookie(db: &Database, request: &Request<'_>) -> Result<User, ()> {
+    let secret = match request.cookies().get_private(SESSION_COOKIE) {
+        Some(cookie) => String::from(cookie.value()),
+        None => return Err(()),
+    };
+
+    let settings = match request.guard::<&State<SessionSettings>>().await {
+        Outcome::Success(value) => value,
+        _ => return Err(()),
+    };
+
+    let mut session = match Session::fetch_by_secret(db, &secret).await {
+        Ok(Some(value)) => value,
+        _ => return Err(()),
+    };
+
+    if settings.is_expired(&session, now()) {
+        let _ = session.delete(db).await;
+        return Err(());
+    }
+
+    if let Err(error) = session.mark_as_seen(db).await {
+        log::warn!(
+            "Could not update last use of session {}: {:?}",
+            session.id,
+            error
+        );
+    }
+
+    match Credentials::fetch_by_username(db, &session.username).await {
+        Ok(Some(creds)) => Ok(User {
+            username: creds.username,
+            role: creds.role,
+        }),
+        _ => Err(()),
+    }
+}
+
+async fn get_user_from_token(db: &Database, header: &str) -> Result<User, ()> {
+    let secret = match header.strip_prefix("Bearer ") {
+        Some(value) => value.trim(),
+        None => return Err(()),
+    };
+
+    let token = match ApiToken::fetch_by_secret(db, secret).await {
+        Ok(Some(value)) => value,
+        _ => return Err(()),
+    };
+
+    let creds = match Credentials::fetch_by_username(db, &token.username).await {
+        Ok(Some(value)) => value,
+        _ => return Err(()),
+    };
+
+    if let Err(error) = token.mark_as_used(db).await {
+        log::warn!(
+            "Could not update last use of token {}: {:?}",
+            token.id,
+            error
+        );
+    }
+
+    // read-only token can't do more than the user itself, so never elevate the role
+    let role = match token.read_only {
+        true => Role::ReadOnly,
+        false => creds.role,
+    };
+
+    Ok(User {
+        username: creds.username,
+        role: role,
+    })
+}
+
+/* Users known to the proxy don't have to be added separately, they get account with
+default role on their first request. Password is random, as it's never used */
+async fn get_user_from_proxy(
+    db: &Database,
+    proxy_auth: &ProxyAuthSettings,
+    request: &Request<'_>,
+) -> Result<User, ()> {
+    let username = match proxy_auth.get_remote_user(request) {
+        Some(value) => value,
+        None => return Err(()),
+    };
+
+    let creds = match Credentials::fetch_by_username(db, username).await {
+        Ok(Some(value)) => value,
+        Ok(None) => match provision_user(db, username, proxy_auth.default_role).await {
+            Ok(value) => value,
+            Err(error) => {
+                log::error!("Could not create user '{}': {:?}", username, error);
+                return Err(());
+            }
+        },
+        Err(_) => return Err(()),
+    };
+
+    Ok(User {
+        username: creds.username,
+        role: creds.role,
+    })
+}
+
+async fn provision_user(db: &Database, username: &str, role: Role) -> anyhow::Result<Credentials> {
+    let pwhash = match hash_password(&generate_token()) {
+        Ok(value) => value,
+        Err(_) => return Err(anyhow::anyhow!("Hashing password failed")),
+    };
+
+    // concurrent first requests of the same user may race here, loser just uses
+    // the account created by the winner
+    match Credentials::create(db, username, &pwhash, role).await {
+        Ok(_) => log::info!("Created user '{}' on first request through proxy", username),
+        Err(error) => log::warn!("Could not create user '{}': {:?}", username, error),
+    };
+
+    match Credentials::fetch_by_username(db, username).await? {
+        Some(creds) => Ok(creds),
+        None => Err(anyhow::anyhow!("User '{}' does not exist", username)),
+    }
+}
+
+/* Request guard for routes that affect data shared by all users, e.g. removing
+series together with all its books */
+pub struct Admin<'r>(pub &'r User);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let user = match request.guard::<&User>().await {
+            Outcome::Success(value) => value,
+            _ => return Outcome::Forward(Status::NotFound),
+        };
+
+        match user.role {
+            Role::Admin => Outcome::Success(Admin(user)),
+            _ => Outcome::Error((Status::Forbidden, ())),
+        }
+    }
+}
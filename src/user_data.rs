@@ -0,0 +1,591 @@
+use serde::{Deserialize, Serialize};
+use sqlx::Connection;
+use std::collections::HashSet;
+use ts_rs::TS;
+
+use crate::books::Book;
+use crate::common::{now, TS_FILE};
+use crate::database::Database;
+use crate::goals;
+use crate::queue::{self, QueueOrder};
+use crate::ratings::{check_rating, BookRating};
+use crate::reads::{is_valid_date, Reading, ReadingParams, ReadingStatus};
+use crate::series::BookSeries;
+use crate::subscriptions::Subscription;
+use crate::user::User;
+
+// bumped whenever the format changes in a way older imports can't handle
+const EXPORT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, TS, Debug, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub struct ExportedReading {
+    pub book_asin: String,
+    pub status: ReadingStatus,
+    pub start_date: Option<String>,
+    pub finish_date: Option<String>,
+    pub progress_percent: Option<u32>,
+    pub progress_location: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, TS, Debug, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub struct ExportedRating {
+    pub book_asin: String,
+    pub rating: Option<u8>,
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, TS, Debug, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub struct ExportedGoal {
+    pub year: i32,
+    pub target: u32,
+}
+
+/* Everything user has set themselves. Books and series are referred to by asin only,
+the other instance scrapes them on its own */
+#[derive(Serialize, Deserialize, TS, Debug, PartialEq)]
+#[ts(export_to = TS_FILE)]
+pub struct UserExport {
+    pub version: u32,
+    pub username: String,
+    #[ts(as = "i32")]
+    pub time_exported: i64,
+    pub subscriptions: Vec<String>,
+    pub readings: Vec<ExportedReading>,
+    pub ratings: Vec<ExportedRating>,
+    pub goals: Vec<ExportedGoal>,
+    // missing from hand-made or partial files, user's queue is left as it is then
+    #[serde(default)]
+    pub queue_order: Option<QueueOrder>,
+    #[serde(default)]
+    pub queue_pinned: Option<Vec<String>>,
+}
+
+#[derive(Serialize, TS, Debug)]
+#[ts(export_to = TS_FILE)]
+pub struct UserImportReport {
+    pub added_subscriptions: u32,
+    pub added_readings: u32,
+    pub updated_ratings: u32,
+    pub updated_goals: u32,
+    // not known on this instance, add the series first and import again
+    pub unknown_series: Vec<String>,
+    pub unknown_books: Vec<String>,
+}
+
+/* Flat version of the export, one row per record. Only the columns that make sense
+for the kind of the record are filled in */
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum RecordKind {
+    Subscription,
+    Reading,
+    Rating,
+    Goal,
+    QueueOrder,
+    QueuePin,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CsvRecord {
+    record: RecordKind,
+    asin: Option<String>,
+    status: Option<ReadingStatus>,
+    start_date: Option<String>,
+    finish_date: Option<String>,
+    progress_percent: Option<u32>,
+    progress_location: Option<u32>,
+    rating: Option<u8>,
+    note: Option<String>,
+    year: Option<i32>,
+    target: Option<u32>,
+    queue_order: Option<QueueOrder>,
+}
+
+impl CsvRecord {
+    fn new(record: RecordKind) -> CsvRecord {
+        CsvRecord {
+            record: record,
+            asin: None,
+            status: None,
+            start_date: None,
+            finish_date: None,
+            progress_percent: None,
+            progress_location: None,
+            rating: None,
+            note: None,
+            year: None,
+            target: None,
+            queue_order: None,
+        }
+    }
+}
+
+pub async fn export(db: &Database, user: &User) -> anyhow::Result<UserExport> {
+    let readings = Reading::fetch_by_user(db, user)
+        .await?
+        .into_iter()
+        .rev() // oldest first, so that they are imported in the original order
+        .map(|reading| ExportedReading {
+            book_asin: reading.book_asin,
+            status: reading.status,
+            start_date: reading.start_date,
+            finish_date: reading.finish_date,
+            progress_percent: reading.progress_percent,
+            progress_location: reading.progress_location,
+        })
+        .collect();
+
+    let ratings = BookRating::fetch_by_user(db, user)
+        .await?
+        .into_iter()
+        .map(|rating| ExportedRating {
+            book_asin: rating.book_asin,
+            rating: rating.rating,
+            note: rating.note,
+        })
+        .collect();
+
+    let goals = goals::fetch_goals(db, user)
+        .await?
+        .into_iter()
+        .map(|goal| ExportedGoal {
+            year: goal.year,
+            target: goal.target,
+        })
+        .collect();
+
+    Ok(UserExport {
+        version: EXPORT_VERSION,
+        username: user.username.clone(),
+        time_exported: now(),
+        subscriptions: Subscription::fetch_series_asins(db, user).await?,
+        readings: readings,
+        ratings: ratings,
+        goals: goals,
+        queue_order: Some(queue::fetch_order(db, user).await?),
+        queue_pinned: Some(queue::fetch_pinned(db, user).await?),
+    })
+}
+
+/* Merges the export into what user already has, so importing the same file twice
+changes nothing. Nothing is ever removed, only ratings, goals and queue settings are
+overwritten, the latter only if the file has them. Written in single transaction, so
+that failure can't leave the import half done */
+pub async fn import(
+    db: &Database,
+    user: &User,
+    data: UserExport,
+) -> anyhow::Result<UserImportReport> {
+    let mut report = UserImportReport {
+        added_subscriptions: 0,
+        added_readings: 0,
+        updated_ratings: 0,
+        updated_goals: 0,
+        unknown_series: Vec::new(),
+        unknown_books: Vec::new(),
+    };
+
+    let known_series: HashSet<String> = BookSeries::fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|series| series.asin)
+        .collect();
+    let known_books: HashSet<String> = Book::fetch_all(db)
+        .await?
+        .into_iter()
+        .map(|book| book.asin)
+        .collect();
+
+    let mut subscribed: HashSet<String> = Subscription::fetch_series_asins(db, user)
+        .await?
+        .into_iter()
+        .collect();
+    let mut existing: HashSet<ReadingKey> = Reading::fetch_by_user(db, user)
+        .await?
+        .into_iter()
+        .map(|reading| {
+            (
+                reading.book_asin,
+                reading.status,
+                reading.start_date,
+                reading.finish_date,
+            )
+        })
+        .collect();
+
+    let mut conn = db.acquire_db_conn().await?;
+    let mut tx = conn.begin().await?;
+
+    for series_asin in data.subscriptions {
+        if !known_series.contains(&series_asin) {
+            report.unknown_series.push(series_asin);
+        } else if subscribed.insert(series_asin.clone()) {
+            Subscription::add_with_conn(&mut tx, user, &series_asin).await?;
+            report.added_subscriptions += 1;
+        }
+    }
+
+    for reading in data.readings {
+        if !known_books.contains(&reading.book_asin) {
+            report.unknown_books.push(reading.book_asin);
+            continue;
+        }
+
+        let params = ReadingParams {
+            status: reading.status,
+            start_date: reading.start_date,
+            finish_date: reading.finish_date,
+            progress_percent: reading.progress_percent,
+            progress_location: reading.progress_location,
+        };
+        let key = (
+            reading.book_asin.clone(),
+            params.status,
+            params.start_date.clone(),
+            params.finish_date.clone(),
+        );
+        if existing.insert(key) {
+            Reading::create_with_conn(&mut tx, user, &reading.book_asin, &params).await?;
+            report.added_readings += 1;
+        }
+    }
+
+    for rating in data.ratings {
+        if !known_books.contains(&rating.book_asin) {
+            report.unknown_books.push(rating.book_asin);
+            continue;
+        }
+        // would delete the rating user already has
+        if rating.rating.is_none() && rating.note.is_none() {
+            continue;
+        }
+
+        BookRating::set_with_conn(
+            &mut tx,
+            user,
+            &rating.book_asin,
+            rating.rating,
+            rating.note.as_deref(),
+        )
+        .await?;
+        report.updated_ratings += 1;
+    }
+
+    for goal in data.goals {
+        goals::set_with_conn(&mut tx, user, goal.year, goal.target).await?;
+        report.updated_goals += 1;
+    }
+
+    if let Some(queue_order) = data.queue_order {
+        queue::set_order_with_conn(&mut tx, user, queue_order).await?;
+    }
+    if let Some(queue_pinned) = data.queue_pinned {
+        queue::set_pinned_with_conn(&mut tx, user, &queue_pinned).await?;
+    }
+
+    tx.commit().await?;
+
+    report.unknown_books.sort();
+    report.unknown_books.dedup();
+
+    Ok(report)
+}
+
+// same book, status and dates means the same reading, progress may have moved since
+type ReadingKey = (String, ReadingStatus, Option<String>, Option<String>);
+
+/* Catches anything that couldn't have been exported by this server, before any of
+it gets imported */
+pub fn check_export(data: &UserExport) -> Result<(), String> {
+    if data.version != EXPORT_VERSION {
+        return Err(format!(
+            "Unsupported export version {}, expected {}!",
+            data.version, EXPORT_VERSION
+        ));
+    }
+
+    for reading in data.readings.iter() {
+        for date in [&reading.start_date, &reading.finish_date]
+            .into_iter()
+            .flatten()
+        {
+            if !is_valid_date(date) {
+                return Err(format!("'{}' is not a valid date!", date));
+            }
+        }
+        if reading
+            .progress_percent
+            .is_some_and(|percent| percent > 100)
+        {
+            return Err(String::from("Progress can't be over 100%!"));
+        }
+    }
+
+    for rating in data.ratings.iter() {
+        check_rating(rating.rating, rating.note.as_deref())?;
+    }
+
+    for goal in data.goals.iter() {
+        goals::check_target(goal.target)?;
+        goals::check_year(goal.year)?;
+    }
+
+    Ok(())
+}
+
+pub fn to_csv(data: &UserExport) -> anyhow::Result<String> {
+    let mut records = Vec::new();
+
+    for series_asin in data.subscriptions.iter() {
+        let mut record = CsvRecord::new(RecordKind::Subscription);
+        record.asin = Some(series_asin.clone());
+        records.push(record);
+    }
+
+    for reading in data.readings.iter() {
+        let mut record = CsvRecord::new(RecordKind::Reading);
+        record.asin = Some(reading.book_asin.clone());
+        record.status = Some(reading.status);
+        record.start_date = reading.start_date.clone();
+        record.finish_date = reading.finish_date.clone();
+        record.progress_percent = reading.progress_percent;
+        record.progress_location = reading.progress_location;
+        records.push(record);
+    }
+
+    for rating in data.ratings.iter() {
+        let mut record = CsvRecord::new(RecordKind::Rating);
+        record.asin = Some(rating.book_asin.clone());
+        record.rating = rating.rating;
+        record.note = rating.note.clone();
+        records.push(record);
+    }
+
+    for goal in data.goals.iter() {
+        let mut record = CsvRecord::new(RecordKind::Goal);
+        record.year = Some(goal.year);
+        record.target = Some(goal.target);
+        records.push(record);
+    }
+
+    if let Some(queue_order) = data.queue_order {
+        let mut record = CsvRecord::new(RecordKind::QueueOrder);
+        record.queue_order = Some(queue_order);
+        records.push(record);
+    }
+
+    // pins are listed in their order
+    for series_asin in data.queue_pinned.iter().flatten() {
+        let mut record = CsvRecord::new(RecordKind::QueuePin);
+        record.asin = Some(series_asin.clone());
+        records.push(record);
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for record in records {
+        writer.serialize(record)?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/* CSV doesn't carry version and username, it's always read as the current version.
+Pins are only imported if there is at least one, as no rows can't be told apart from
+file that doesn't include them */
+pub fn from_csv(content: &str, username: &str) -> anyhow::Result<UserExport> {
+    let mut data = UserExport {
+        version: EXPORT_VERSION,
+        username: username.to_string(),
+        time_exported: now(),
+        subscriptions: Vec::new(),
+        readings: Vec::new(),
+        ratings: Vec::new(),
+        goals: Vec::new(),
+        queue_order: None,
+        queue_pinned: None,
+    };
+
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    for (index, record) in reader.deserialize::<CsvRecord>().enumerate() {
+        let record = record?;
+        // header is the first line
+        let line = index + 2;
+        let missing = |column: &str| anyhow::anyhow!("Line {} is missing {}", line, column);
+
+        match record.record {
+            RecordKind::Subscription => {
+                data.subscriptions
+                    .push(record.asin.ok_or_else(|| missing("asin"))?);
+            }
+            RecordKind::Reading => data.readings.push(ExportedReading {
+                book_asin: record.asin.ok_or_else(|| missing("asin"))?,
+                status: record.status.ok_or_else(|| missing("status"))?,
+                start_date: record.start_date,
+                finish_date: record.finish_date,
+                progress_percent: record.progress_percent,
+                progress_location: record.progress_location,
+            }),
+            RecordKind::Rating => data.ratings.push(ExportedRating {
+                book_asin: record.asin.ok_or_else(|| missing("asin"))?,
+                rating: record.rating,
+                note: record.note,
+            }),
+            RecordKind::Goal => data.goals.push(ExportedGoal {
+                year: record.year.ok_or_else(|| missing("year"))?,
+                target: record.target.ok_or_else(|| missing("target"))?,
+            }),
+            RecordKind::QueueOrder => {
+                data.queue_order = Some(record.queue_order.ok_or_else(|| missing("queue_order"))?);
+            }
+            RecordKind::QueuePin => {
+                data.queue_pinned
+                    .get_or_insert_with(Vec::new)
+                    .push(record.asin.ok_or_else(|| missing("asin"))?);
+            }
+        };
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::user::Role;
+
+    fn example() -> UserExport {
+        UserExport {
+            version: EXPORT_VERSION,
+            username: String::from("user"),
+            time_exported: 0,
+            subscriptions: vec![String::from("B0SERIES01"), String::from("B0SERIES02")],
+            readings: vec![
+                ExportedReading {
+                    book_asin: String::from("B0BOOK0001"),
+                    status: ReadingStatus::Read,
+                    start_date: Some(String::from("2025-01-01")),
+                    finish_date: Some(String::from("2025-01-10")),
+                    progress_percent: Some(100),
+                    progress_location: None,
+                },
+                ExportedReading {
+                    book_asin: String::from("B0BOOK0002"),
+                    status: ReadingStatus::Reading,
+                    start_date: Some(String::from("2025-01-11")),
+                    finish_date: None,
+                    progress_percent: Some(30),
+                    progress_location: Some(1234),
+                },
+            ],
+            ratings: vec![ExportedRating {
+                book_asin: String::from("B0BOOK0001"),
+                rating: Some(4),
+                note: Some(String::from("Good, but \"that\" ending,\nreally?")),
+            }],
+            goals: vec![ExportedGoal {
+                year: 2025,
+                target: 24,
+            }],
+            queue_order: Some(QueueOrder::Manual),
+            queue_pinned: Some(vec![String::from("B0SERIES02"), String::from("B0SERIES01")]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import() {
+        let db = Database::init_for_tests().await.unwrap();
+        let mut conn = db.acquire_db_conn().await.unwrap();
+        sqlx::raw_sql(
+            "INSERT INTO credentials (username, pwhash, role) VALUES ('user', '', 'member');
+            INSERT INTO series (asin, name, time_first_seen) VALUES ('B0SERIES01', 'Series', 0);
+            INSERT INTO books (asin, series_asin, ordinal, title, author, time_first_seen)
+            VALUES ('B0BOOK0001', 'B0SERIES01', 1, 'Book', 'Author', 0);",
+        )
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        drop(conn);
+
+        let user = User {
+            username: String::from("user"),
+            role: Role::Member,
+        };
+        BookRating::set(&db, &user, "B0BOOK0001", Some(5), None)
+            .await
+            .unwrap();
+
+        let mut data = example();
+        data.ratings[0].rating = None;
+        data.ratings[0].note = None;
+
+        let report = import(&db, &user, data).await.unwrap();
+        assert_eq!(report.added_subscriptions, 1);
+        assert_eq!(report.added_readings, 1);
+        assert_eq!(report.updated_ratings, 0);
+        assert_eq!(report.unknown_series, vec![String::from("B0SERIES02")]);
+        assert_eq!(report.unknown_books, vec![String::from("B0BOOK0002")]);
+
+        // empty rating in the file doesn't remove the existing one
+        let ratings = BookRating::fetch_by_user(&db, &user).await.unwrap();
+        assert_eq!(ratings[0].rating, Some(5));
+
+        let report = import(&db, &user, example()).await.unwrap();
+        assert_eq!(report.added_subscriptions, 0);
+        assert_eq!(report.added_readings, 0);
+        assert_eq!(report.updated_ratings, 1);
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let data = example();
+        let csv = to_csv(&data).unwrap();
+
+        let mut parsed = from_csv(&csv, "user").unwrap();
+        parsed.time_exported = data.time_exported;
+        assert_eq!(parsed, data);
+    }
+
+    #[test]
+    fn test_from_csv_without_queue() {
+        let csv = "record,asin,status,start_date,finish_date,progress_percent,\
+            progress_location,rating,note,year,target,queue_order\n\
+            reading,B0BOOK0001,read,,,,,,,,,\n";
+
+        let parsed = from_csv(csv, "user").unwrap();
+        assert_eq!(parsed.readings.len(), 1);
+        assert_eq!(parsed.queue_order, None);
+        assert_eq!(parsed.queue_pinned, None);
+    }
+
+    #[test]
+    fn test_from_csv_rejects_incomplete_records() {
+        let header = "record,asin,status,start_date,finish_date,progress_percent,\
+            progress_location,rating,note,year,target,queue_order";
+
+        let csv = format!("{}\nreading,B0BOOK0001,,,,,,,,,,\n", header);
+        assert!(from_csv(&csv, "user").is_err());
+
+        let csv = format!("{}\nsomething,B0BOOK0001,,,,,,,,,,\n", header);
+        assert!(from_csv(&csv, "user").is_err());
+    }
+
+    #[test]
+    fn test_check_export() {
+        assert!(check_export(&example()).is_ok());
+
+        let mut future_version = example();
+        future_version.version = EXPORT_VERSION + 1;
+        assert!(check_export(&future_version).is_err());
+
+        let mut invalid_date = example();
+        invalid_date.readings[0].finish_date = Some(String::from("2025-02-31"));
+        assert!(check_export(&invalid_date).is_err());
+
+        let mut invalid_rating = example();
+        invalid_rating.ratings[0].rating = Some(9);
+        assert!(check_export(&invalid_rating).is_err());
+    }
+}